@@ -25,5 +25,19 @@ pub enum AssetsCall {
         #[codec(compact)]
         amount: Balance,
     },
+    /// Transfer some asset balance from a previously delegated account to some
+    /// third-party account.
+    ///
+    /// Used to pull tokens the owner approved to the contract through
+    /// `approve_transfer`.
+    #[codec(index = 25)]
+    TransferApproved {
+        #[codec(compact)]
+        id: u128,
+        owner: MultiAddress<AccountId, ()>,
+        destination: MultiAddress<AccountId, ()>,
+        #[codec(compact)]
+        amount: Balance,
+    },
 }
 
@@ -24,6 +24,32 @@ export function decode(data) {
         "Error::LoanCollateralInsufficient",
         "Error::LoanAlreadyExist",
         "Error::LoanNotFound",
+        "Error::RoleNotGranted",
+        "Error::PendingTransferNotFound",
+        "Error::InvalidParameter",
+        "Error::BankNotEmpty",
+        "Error::LoansOutstanding",
+        "Error::ResetNotRequested",
+        "Error::ChangeNotFound",
+        "Error::TimelockNotExpired",
+        "Error::TooManyPendingChanges",
+        "Error::ProposalNotFound",
+        "Error::ProposalExpired",
+        "Error::ProposalAlreadyApproved",
+        "Error::AccountDormant",
+        "Error::AccountClosed",
+        "Error::InvalidStatusTransition",
+        "Error::AccountHasLoan",
+        "Error::AccountNotEmpty",
+        "Error::BatchTooLarge",
+        "Error::AllowanceInsufficient",
+        "Error::StandingOrderNotFound",
+        "Error::TooManyStandingOrders",
+        "Error::EscrowNotFound",
+        "Error::TooManyEscrows",
+        "Error::RolloverPending",
+        "Error::TooManyApprovers",
+        "Error::DistributionInProgress",
     ]; 
 
     const successMap = [
@@ -38,6 +64,12 @@ export function decode(data) {
         "Success::LoanFullyPaidSuccess",
         "Success::LoanPaymentSuccess",
         "Success::LoanLiquidationSuccess",
+        "Success::BankResetRequestSuccess",
+        "Success::BankResetCancelSuccess",
+        "Success::BankResetSuccess",
+        "Success::ApproversSetSuccess",
+        "Success::AccountOpenSuccess",
+        "Success::AccountCloseSuccess",
     ];     
 
     //console.log(payload);
@@ -30,6 +30,7 @@ const manager = "XqEqTNDXfTjWbSaCuio852eNsDRvCW73iuPSywizW7FNKeZ4L"
 const maximumAccounts = 1000;
 const threshold = 20;
 const dailyBlocks = 14400;
+const emitErrors = false;
 
 // Caller
 const keyring = new Keyring({ type: "sr25519" });
@@ -44,7 +45,8 @@ await new Promise(async (resolve, reject) => {
       manager,
       maximumAccounts,
       threshold,
-      dailyBlocks
+      dailyBlocks,
+      emitErrors
     ).signAndSend(alice, ({ status, events, dispatchError }) => {    
       console.log("Status:", status?.type);
       if(events?.length > 0) {
@@ -29,6 +29,59 @@ pub enum Error {
     LoanAlreadyExist,
     /// Loan not found
     LoanNotFound,
+    /// Role not granted to the account
+    RoleNotGranted,
+    /// No pending ownership or manager transfer
+    PendingTransferNotFound,
+    /// Invalid parameter value
+    InvalidParameter,
+    /// Bank still has accounts or balances
+    BankNotEmpty,
+    /// Bank still has active loans
+    LoansOutstanding,
+    /// No pending reset request, or confirmed in the same block
+    ResetNotRequested,
+    /// Time-locked change not found
+    ChangeNotFound,
+    /// Time-locked change delay has not passed yet
+    TimelockNotExpired,
+    /// Caller has too many pending time-locked changes
+    TooManyPendingChanges,
+    /// Proposal not found
+    ProposalNotFound,
+    /// Proposal expired
+    ProposalExpired,
+    /// Proposal already approved by the approver
+    ProposalAlreadyApproved,
+    /// Account dormant
+    AccountDormant,
+    /// Account closed
+    AccountClosed,
+    /// Account status transition not allowed
+    InvalidStatusTransition,
+    /// Account has an active loan
+    AccountHasLoan,
+    /// Account still has a locked balance or a free balance above the dust limit
+    AccountNotEmpty,
+    /// Batch exceeds the maximum batch size
+    BatchTooLarge,
+    /// Spender allowance is insufficient
+    AllowanceInsufficient,
+    /// Standing order does not exist
+    StandingOrderNotFound,
+    /// Account has too many standing orders
+    TooManyStandingOrders,
+    /// Escrow does not exist
+    EscrowNotFound,
+    /// Payer has too many open escrows
+    TooManyEscrows,
+    /// Account is too many statement periods or rate tier versions behind, roll it
+    /// over first
+    RolloverPending,
+    /// Too many multisig approvers
+    TooManyApprovers,
+    /// An interest pool distribution is in progress
+    DistributionInProgress,
 }
 
 /// Runtime call execution error
@@ -10,10 +10,47 @@ pub mod errors;
 mod bank {
 
     use ink::prelude::vec::Vec;
+    use ink::storage::{Lazy, Mapping};
+    use sp_runtime::helpers_128bit::multiply_by_rational_with_rounding;
+    use sp_runtime::Rounding;
 
     use crate::errors::{Error, RuntimeError, ContractError};
     use crate::assets::{AssetsCall, RuntimeCall};
 
+    /// Maximum number of pending time-locked changes per proposer
+    const MAXIMUM_PENDING_CHANGES: usize = 16;
+
+    /// Maximum number of multisig approvers
+    const MAXIMUM_APPROVERS: usize = 32;
+
+    /// Fixed point scale of the interest indices and interest days (1.0)
+    const INTEREST_INDEX_SCALE: u128 = 1_000_000_000_000_000_000;
+
+    /// Basis points in 100%
+    const BASIS_POINTS: u128 = 10_000;
+
+    /// Days per year, used to convert annual rates to daily rates
+    const DAYS_PER_YEAR: u128 = 365;
+
+    /// Maximum number of rate tiers per product
+    const MAXIMUM_RATE_TIERS: usize = 8;
+
+    /// Maximum number of rate tier versions an interest settlement follows, a ledger
+    /// further behind must be rolled over first
+    const MAXIMUM_RATE_VERSIONS: u32 = 32;
+
+    /// Maximum number of standing orders per account
+    const MAXIMUM_STANDING_ORDERS: usize = 16;
+
+    /// Maximum number of open escrows per party
+    const MAXIMUM_ESCROWS: usize = 16;
+
+    /// Maximum number of statement periods rolled over when an account is touched
+    const MAXIMUM_ROLLOVER_PERIODS: u32 = 12;
+
+    /// Default maximum number of transfers in a batch transfer
+    const DEFAULT_MAX_BATCH_SIZE: u16 = 100;
+
     /// Success Messages
     #[derive(scale::Encode, scale::Decode, Debug, Clone, PartialEq, Eq)]
     #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
@@ -40,6 +77,18 @@ mod bank {
         LoanPaymentSuccess,
         /// Loan liquidation success
         LoanLiquidationSuccess,
+        /// Bank reset request successful
+        BankResetRequestSuccess,
+        /// Bank reset cancel successful
+        BankResetCancelSuccess,
+        /// Bank reset successful
+        BankResetSuccess,
+        /// Multisig approvers set successful
+        ApproversSetSuccess,
+        /// Account open successful
+        AccountOpenSuccess,
+        /// Account close successful
+        AccountCloseSuccess,
     }    
 
     /// Bank transaction status
@@ -58,6 +107,422 @@ mod bank {
         status: BankTransactionStatus,
     }     
 
+    /// Bank roles.  The manager holds every role.
+    #[derive(scale::Encode, scale::Decode, Debug, Clone, Copy, PartialEq, Eq)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout))]
+    pub enum Role {
+        /// Deposits, withdrawals and credits
+        Teller,
+        /// Loan applications and loan payments
+        LoanOfficer,
+        /// Loan liquidations (price oracle/keeper)
+        Oracle,
+        /// Interest rate setting
+        InterestOfficer,
+        /// Closing the bank
+        Pauser,
+        /// Closing statement periods (keeper)
+        Keeper,
+    }
+
+    /// Role granted event
+    #[ink(event)]
+    pub struct RoleGranted {
+        #[ink(topic)]
+        role: Role,
+        #[ink(topic)]
+        account: AccountId,
+        sender: AccountId,
+    }
+
+    /// Role revoked event (also emitted when a role is renounced)
+    #[ink(event)]
+    pub struct RoleRevoked {
+        #[ink(topic)]
+        role: Role,
+        #[ink(topic)]
+        account: AccountId,
+        sender: AccountId,
+    }
+
+    /// Ownership transfer started event
+    #[ink(event)]
+    pub struct OwnershipTransferStarted {
+        #[ink(topic)]
+        previous_owner: AccountId,
+        #[ink(topic)]
+        new_owner: AccountId,
+    }
+
+    /// Ownership transferred event
+    #[ink(event)]
+    pub struct OwnershipTransferred {
+        #[ink(topic)]
+        previous_owner: AccountId,
+        #[ink(topic)]
+        new_owner: AccountId,
+    }
+
+    /// Ownership transfer cancelled event
+    #[ink(event)]
+    pub struct OwnershipTransferCancelled {
+        #[ink(topic)]
+        owner: AccountId,
+        #[ink(topic)]
+        cancelled_owner: AccountId,
+    }
+
+    /// Manager proposed event
+    #[ink(event)]
+    pub struct ManagerProposed {
+        #[ink(topic)]
+        previous_manager: AccountId,
+        #[ink(topic)]
+        new_manager: AccountId,
+    }
+
+    /// Manager changed event
+    #[ink(event)]
+    pub struct ManagerChanged {
+        #[ink(topic)]
+        previous_manager: AccountId,
+        #[ink(topic)]
+        new_manager: AccountId,
+    }
+
+    /// Manager proposal cancelled event
+    #[ink(event)]
+    pub struct ManagerProposalCancelled {
+        #[ink(topic)]
+        manager: AccountId,
+        #[ink(topic)]
+        cancelled_manager: AccountId,
+    }
+
+    /// Bank parameters
+    #[derive(scale::Encode, scale::Decode, Debug, Clone, Copy, PartialEq, Eq)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum Parameter {
+        AssetId,
+        LoanAssetId,
+        MaximumAccounts,
+        Threshold,
+        DailyBlocks,
+        DustLimit,
+        MaxBatchSize,
+        EmitErrors,
+    }
+
+    /// Parameter changed event
+    #[ink(event)]
+    pub struct ParameterChanged {
+        #[ink(topic)]
+        parameter: Parameter,
+        old_value: u128,
+        new_value: u128,
+    }
+
+    /// Time-locked changes
+    #[derive(scale::Encode, scale::Decode, Debug, Clone, PartialEq, Eq)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout))]
+    pub enum TimelockedChange {
+        /// Loan price threshold
+        Threshold(u16),
+        /// Daily blocks
+        DailyBlocks(u16),
+        /// Manager proposal (the new manager still has to accept)
+        Manager(AccountId),
+        /// Timelock delay in blocks
+        TimelockDelay(BlockNumber),
+    }
+
+    /// Pending time-locked change
+    #[derive(scale::Encode, scale::Decode, Debug, Clone, PartialEq, Eq)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout))]
+    pub struct PendingChange {
+        /// Change id
+        pub id: u32,
+        /// Change
+        pub change: TimelockedChange,
+        /// Account that queued the change
+        pub proposer: AccountId,
+        /// Earliest execution block
+        pub eta: BlockNumber,
+    }
+
+    /// Change queued event
+    #[ink(event)]
+    pub struct ChangeQueued {
+        #[ink(topic)]
+        id: u32,
+        change: TimelockedChange,
+        eta: BlockNumber,
+    }
+
+    /// Change executed event
+    #[ink(event)]
+    pub struct ChangeExecuted {
+        #[ink(topic)]
+        id: u32,
+    }
+
+    /// Change cancelled event
+    #[ink(event)]
+    pub struct ChangeCancelled {
+        #[ink(topic)]
+        id: u32,
+    }
+
+    /// Actions that need the approval of the approvers above the approval amount
+    #[derive(scale::Encode, scale::Decode, Debug, Clone, PartialEq, Eq)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout))]
+    pub enum ProposalAction {
+        /// Deposit to an account
+        Deposit { account: AccountId, amount: u128 },
+        /// Withdraw from an account
+        Withdraw { account: AccountId, amount: u128 },
+        /// Credit to an account
+        Credit { account: AccountId, amount: u128 },
+        /// Loan application
+        LoanApplication { account: AccountId, loan_amount: u128, price: u128, collateral: u128 },
+    }
+
+    /// Multisig proposal
+    #[derive(scale::Encode, scale::Decode, Debug, Clone, PartialEq, Eq)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout))]
+    pub struct Proposal {
+        /// Proposal id
+        pub id: u32,
+        /// Proposed action
+        pub action: ProposalAction,
+        /// Account that proposed the action
+        pub proposer: AccountId,
+        /// Approvers that approved the proposal
+        pub approvals: Vec<AccountId>,
+        /// Block after which the proposal can no longer be approved
+        pub expiry_block: BlockNumber,
+    }
+
+    /// Proposal created event
+    #[ink(event)]
+    pub struct ProposalCreated {
+        #[ink(topic)]
+        id: u32,
+        #[ink(topic)]
+        proposer: AccountId,
+        action: ProposalAction,
+        expiry_block: BlockNumber,
+    }
+
+    /// Proposal approved event
+    #[ink(event)]
+    pub struct ProposalApproved {
+        #[ink(topic)]
+        id: u32,
+        #[ink(topic)]
+        approver: AccountId,
+        approvals: u8,
+    }
+
+    /// Proposal executed event
+    #[ink(event)]
+    pub struct ProposalExecuted {
+        #[ink(topic)]
+        id: u32,
+    }
+
+    /// Proposal cancelled event
+    #[ink(event)]
+    pub struct ProposalCancelled {
+        #[ink(topic)]
+        id: u32,
+    }
+
+    /// Batch transfer result for a recipient
+    #[derive(scale::Encode, scale::Decode, Clone, Debug, PartialEq, Eq)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub struct TransferReceipt {
+        /// Recipient account
+        pub to: AccountId,
+        /// Amount transferred
+        pub amount: u128,
+        /// Recipient free balance after the transfer
+        pub balance: u128,
+    }
+
+    /// Internal transfer event
+    #[ink(event)]
+    pub struct Transfer {
+        #[ink(topic)]
+        from: AccountId,
+        #[ink(topic)]
+        to: AccountId,
+        amount: u128,
+    }
+
+    /// Allowance set event
+    #[ink(event)]
+    pub struct Approval {
+        #[ink(topic)]
+        owner: AccountId,
+        #[ink(topic)]
+        spender: AccountId,
+        amount: u128,
+    }
+
+    /// Allowances of every epoch, (epoch, owner, spender) -> amount
+    type Allowances = Mapping<(u32, AccountId, AccountId), u128>;
+
+    /// Standing order (recurring payment between ledgers)
+    #[derive(scale::Encode, scale::Decode, Debug, Clone, PartialEq, Eq)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout))]
+    pub struct StandingOrder {
+        /// Standing order id
+        pub id: u32,
+        /// Paying account
+        pub owner: AccountId,
+        /// Receiving account
+        pub recipient: AccountId,
+        /// Amount paid on every execution
+        pub amount: u128,
+        /// Interval between executions in days (daily blocks)
+        pub interval_days: u16,
+        /// Block at which the next execution is due
+        pub next_execution_block: BlockNumber,
+        /// Block after which the order is no longer executed
+        pub end_block: BlockNumber,
+        /// Number of executions skipped for insufficient balance or inactive accounts
+        pub failures: u32,
+    }
+
+    /// Standing order created event
+    #[ink(event)]
+    pub struct StandingOrderCreated {
+        #[ink(topic)]
+        id: u32,
+        #[ink(topic)]
+        owner: AccountId,
+        #[ink(topic)]
+        recipient: AccountId,
+        amount: u128,
+        interval_days: u16,
+        end_block: BlockNumber,
+    }
+
+    /// Standing order executed event
+    #[ink(event)]
+    pub struct StandingOrderExecuted {
+        #[ink(topic)]
+        id: u32,
+        amount: u128,
+    }
+
+    /// Standing order failed event, the execution is skipped
+    #[ink(event)]
+    pub struct StandingOrderFailed {
+        #[ink(topic)]
+        id: u32,
+        failures: u32,
+    }
+
+    /// Standing order cancelled event
+    #[ink(event)]
+    pub struct StandingOrderCancelled {
+        #[ink(topic)]
+        id: u32,
+    }
+
+    /// Escrow (conditional payment locked on the payer's ledger)
+    #[derive(scale::Encode, scale::Decode, Debug, Clone, PartialEq, Eq)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout))]
+    pub struct Escrow {
+        /// Escrow id
+        pub id: u32,
+        /// Paying account, the amount is locked on its ledger
+        pub payer: AccountId,
+        /// Receiving account
+        pub payee: AccountId,
+        /// Account that can release or refund the escrow
+        pub arbiter: AccountId,
+        /// Escrowed amount
+        pub amount: u128,
+        /// Block after which anyone can refund the escrow
+        pub expiry_block: BlockNumber,
+    }
+
+    /// Escrow created event
+    #[ink(event)]
+    pub struct EscrowCreated {
+        #[ink(topic)]
+        id: u32,
+        #[ink(topic)]
+        payer: AccountId,
+        #[ink(topic)]
+        payee: AccountId,
+        arbiter: AccountId,
+        amount: u128,
+        expiry_block: BlockNumber,
+    }
+
+    /// Escrow released (paid to the payee) event
+    #[ink(event)]
+    pub struct EscrowReleased {
+        #[ink(topic)]
+        id: u32,
+        #[ink(topic)]
+        operator: AccountId,
+    }
+
+    /// Escrow refunded (returned to the payer) event
+    #[ink(event)]
+    pub struct EscrowRefunded {
+        #[ink(topic)]
+        id: u32,
+        #[ink(topic)]
+        operator: AccountId,
+    }
+
+    /// Loan liquidation event, emitted for every liquidated loan
+    #[ink(event)]
+    pub struct LoanLiquidated {
+        #[ink(topic)]
+        account: AccountId,
+        seized: u128,
+        bad_debt: u128,
+    }
+
+    /// Treasury withdrawn event
+    #[ink(event)]
+    pub struct TreasuryWithdrawn {
+        #[ink(topic)]
+        to: AccountId,
+        amount: u128,
+    }
+
+    /// Account status
+    #[derive(scale::Encode, scale::Decode, Debug, Clone, Copy, PartialEq, Eq)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout))]
+    pub enum AccountStatus {
+        /// All operations allowed
+        Active,
+        /// Frozen by the manager, no operations allowed
+        Frozen,
+        /// Inactive account, reactivated by a deposit or by the manager
+        Dormant,
+        /// Closed account
+        Closed,
+    }
+
+    /// Account status changed event
+    #[ink(event)]
+    pub struct AccountStatusChanged {
+        #[ink(topic)]
+        account: AccountId,
+        from: AccountStatus,
+        to: AccountStatus,
+        reason: u8,
+    }
+
     /// Bank ledger
     #[derive(scale::Encode, scale::Decode, Clone, Debug, PartialEq, Eq)]
     #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout))]
@@ -66,22 +531,205 @@ mod bank {
         pub account: AccountId,
         /// Free balance
         pub balance: u128,
-        /// Average daily balance.  Computed every incoming and outgoing transactions
+        /// Locked balance (loan collateral and open escrows).  Not available for withdraw
+        /// and debit.
+        pub locked: u128,
+        /// Average daily balance (time-weighted average of the free balance over the
+        /// current statement period).  Recomputed every incoming and outgoing transactions:
+        ///    adb = (cumulative_balance - period_start_cumulative)
+        ///              / [current_block - period_start_block]
         pub adb: u128,
         /// Average daily balance beginning block.  Sets upon creation of the ledger.
-        /// This is used to compute for the time-weighted average balance:
-        ///    adb = (balance x [current_block - adb_beginning_block]) / bank.daily_blocks
         pub adb_beginning_block: u128,
-        /// Status (0-Frozen, 1-Liquid)
-        pub status: u8,
+        /// Running sum of the free balance held every block (balance-blocks) since the
+        /// beginning block, accumulated up to `adb_last_block`
+        pub cumulative_balance: u128,
+        /// Block up to which `cumulative_balance` is accumulated
+        pub adb_last_block: u128,
+        /// Number of ADB checkpoints of the ledger
+        pub checkpoint_count: u32,
+        /// Statement period the ADB is computed over
+        pub period: u32,
+        /// Block the ADB period started (period start or ledger creation)
+        pub period_start_block: u128,
+        /// Cumulative balance-blocks at the period start block
+        pub period_start_cumulative: u128,
+        /// Interest credited during the period
+        pub period_interest: u128,
+        /// Product (e.g. savings, checking), selects the interest rate tiers
+        pub product: u8,
+        /// Version of the product rate tiers the interest was last settled at
+        pub rate_version: u32,
+        /// Rate tier the interest was last settled at
+        pub rate_tier: u8,
+        /// Interest index of the rate tier the interest was last settled at
+        pub interest_index: u128,
+        /// Interest earned but not paid yet, paid once the interest pool is funded
+        pub deferred_interest: u128,
+        /// Interest earned under older rate tier versions, paid with the next settlement
+        /// (see `Bank::rollover_account`)
+        pub accrued_interest: u128,
+        /// Status
+        pub status: AccountStatus,
+        /// Optional account metadata (e.g. hash of off-chain KYC documents)
+        pub metadata: Option<Hash>,
     }        
 
+    /// ADB checkpoint, recorded every block the free balance of a ledger changes.  Used
+    /// to compute the time-weighted average balance over any period.
+    #[derive(scale::Encode, scale::Decode, Clone, Debug, PartialEq, Eq)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout))]
+    pub struct AdbCheckpoint {
+        /// Block of the balance change
+        pub block: u128,
+        /// Cumulative balance-blocks up to the block
+        pub cumulative_balance: u128,
+        /// Free balance from the block on
+        pub balance: u128,
+    }
+
+    /// Account statement of a closed statement period
+    #[derive(scale::Encode, scale::Decode, Clone, Debug, PartialEq, Eq)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout))]
+    pub struct Statement {
+        /// Statement period
+        pub period: u32,
+        /// First block of the period for the account
+        pub start_block: u128,
+        /// Block the period was closed (excluded)
+        pub end_block: u128,
+        /// Free balance at the start of the period
+        pub opening_balance: u128,
+        /// Free balance at the end of the period
+        pub closing_balance: u128,
+        /// Average daily balance over the period
+        pub adb: u128,
+        /// Interest credited during the period
+        pub interest_paid: u128,
+    }
+
+    /// Statement period closed event
+    #[ink(event)]
+    pub struct PeriodClosed {
+        #[ink(topic)]
+        period: u32,
+        end_block: BlockNumber,
+    }
+
+    /// Interest rate tier: the annual rate of balances up to `up_to`
+    #[derive(scale::Encode, scale::Decode, Clone, Debug, PartialEq, Eq)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout))]
+    pub struct RateTier {
+        /// Upper bound (inclusive) of the balance band
+        pub up_to: u128,
+        /// Annual interest rate in basis points (100 is 1%)
+        pub rate_bps: u16,
+    }
+
+    /// Rate tiers of a product version.  The tiers apply from `start_days` until the
+    /// next version of the product rate tiers takes effect.
+    #[derive(scale::Encode, scale::Decode, Clone, Debug, Default, PartialEq, Eq)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout))]
+    pub struct RateSchedule {
+        /// Rate tiers
+        pub tiers: Vec<RateTier>,
+        /// Interest days (see `Bank::interest_days`) the tiers took effect
+        pub start_days: u128,
+    }
+
+    /// Rate tiers of every product version, (product, version) -> schedule
+    type RateSchedules = Mapping<(u8, u32), RateSchedule>;
+
+    /// Rate tiers set event
+    #[ink(event)]
+    pub struct RateTiersSet {
+        #[ink(topic)]
+        product: u8,
+        version: u32,
+        tiers: Vec<RateTier>,
+    }
+
+    /// Account product changed event
+    #[ink(event)]
+    pub struct AccountProductChanged {
+        #[ink(topic)]
+        account: AccountId,
+        product: u8,
+    }
+
+    /// Interest pool funded event
+    #[ink(event)]
+    pub struct InterestPoolFunded {
+        #[ink(topic)]
+        funder: AccountId,
+        amount: u128,
+    }
+
+    /// Interest pool distribution in progress (see `Bank::distribute_interest_pool`)
+    #[derive(scale::Encode, scale::Decode, Clone, Debug, Default, PartialEq, Eq)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout))]
+    pub struct InterestDistribution {
+        /// Distribution id, keys the balance-blocks counted per ledger
+        pub id: u32,
+        /// Closed statement period the shares are computed over
+        pub period: u32,
+        /// Amount set aside from the interest pool
+        pub amount: u128,
+        /// Balance-blocks of the active ledgers over the period, summed so far
+        pub total_balance_blocks: u128,
+        /// Whether every ledger is summed and the shares are being paid
+        pub paying: bool,
+        /// Ledger index to resume from
+        pub cursor: u32,
+        /// Amount paid so far
+        pub distributed: u128,
+        /// Number of ledgers paid so far
+        pub accounts: u32,
+    }
+
+    /// Interest pool distributed event
+    #[ink(event)]
+    pub struct InterestPoolDistributed {
+        #[ink(topic)]
+        period: u32,
+        distributed: u128,
+        accounts: u32,
+        dust: u128,
+    }
+
+    /// Interest pool distribution aborted event
+    #[ink(event)]
+    pub struct InterestDistributionAborted {
+        #[ink(topic)]
+        period: u32,
+        distributed: u128,
+        returned: u128,
+    }
+
+    /// Closed account record (tombstone), kept for audit
+    #[derive(scale::Encode, scale::Decode, Clone, Debug, PartialEq, Eq)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout))]
+    pub struct ClosedAccount {
+        /// Account address
+        pub account: AccountId,
+        /// Account metadata
+        pub metadata: Option<Hash>,
+        /// Block the ledger was opened
+        pub opened_block: u128,
+        /// Block the ledger was closed
+        pub closed_block: u128,
+        /// Account that closed the ledger
+        pub closed_by: AccountId,
+        /// Dust swept to the holder on closing
+        pub swept: u128,
+    }
+
     #[derive(scale::Encode, scale::Decode, Clone, Debug, PartialEq, Eq)]
     #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout))]
     pub struct Loan {
         /// Account address
         pub account: AccountId,
-        /// Collateral
+        /// Collateral (locked on the account ledger)
         pub collateral: u128,
         /// Loan amount
         pub loan_amount: u128,
@@ -104,16 +752,130 @@ mod bank {
         pub owner: AccountId,
         /// Bank manager
         pub manager: AccountId,
+        /// Pending owner (two-step ownership transfer)
+        pub pending_owner: Option<AccountId>,
+        /// Pending manager (two-step manager transfer)
+        pub pending_manager: Option<AccountId>,
         /// Maximum accounts the bank ledger can handle
         pub maximum_accounts: u16,
         /// Daily blocks
         pub daily_blocks: u16,
         /// Threshold (loan price threshold in percentage)
         pub threshold: u16,
-        /// Bank ledgers
-        pub ledgers: Vec<Ledger>,
-        /// Bank loans
-        pub loans: Vec<Loan>,
+        /// Bank ledgers (epoch, account) -> ledger
+        pub ledgers: Mapping<(u32, AccountId), Ledger>,
+        /// Ledger account index (position -> account) used for bank-wide iteration
+        pub ledger_accounts: Mapping<u32, AccountId>,
+        /// Ledger account positions (epoch, account) -> position used for removal
+        pub ledger_positions: Mapping<(u32, AccountId), u32>,
+        /// Number of ledger accounts
+        pub ledger_count: u32,
+        /// Ledger epoch, a reset moves to a new epoch to clear every ledger and loan
+        pub ledger_epoch: u32,
+        /// Free and locked balance of every ledger
+        pub total_balances: u128,
+        /// ADB checkpoints (account, checkpoint number) -> checkpoint
+        pub adb_checkpoints: Mapping<(AccountId, u32), AdbCheckpoint>,
+        /// Current statement period
+        pub current_period: u32,
+        /// Start block of the current statement period
+        pub period_start_block: BlockNumber,
+        /// End block of every closed statement period
+        pub period_end_blocks: Mapping<u32, BlockNumber>,
+        /// Interest days (see `interest_days`) at the end of every closed statement period
+        pub period_end_days: Mapping<u32, u128>,
+        /// Account statements (account, period) -> statement, snapshotted when the
+        /// account is next touched after the period is closed
+        pub statements: Mapping<(AccountId, u32), Statement>,
+        /// Current rate tiers version of every product
+        pub rate_versions: Mapping<u8, u32>,
+        /// Rate tiers (product, version) -> schedule
+        pub rate_schedules: RateSchedules,
+        /// Interest days elapsed up to `interest_days_block` (fixed point,
+        /// `INTEREST_INDEX_SCALE` is one day).  Interest accrues per day, so a change of
+        /// the daily blocks does not change the annual rates.
+        pub interest_days: u128,
+        /// Block up to which `interest_days` is counted
+        pub interest_days_block: BlockNumber,
+        /// Interest pool (assets funded for interest and distribution, including carried
+        /// dust)
+        pub interest_pool: u128,
+        /// Rounding dust of the last distribution, carried forward in the pool
+        pub interest_pool_dust: u128,
+        /// Interest owed to the ledgers (sum of their deferred interest)
+        pub interest_owed: u128,
+        /// Interest pool distribution in progress
+        pub interest_distribution: Option<InterestDistribution>,
+        /// Balance-blocks counted per ledger by a distribution, keyed by distribution id.
+        /// Only the ledgers counted are paid.
+        pub distribution_balances: Mapping<(u32, AccountId), u128>,
+        /// Next interest distribution id
+        pub next_distribution_id: u32,
+        /// Closed accounts (tombstones)
+        pub closed_accounts: Mapping<AccountId, ClosedAccount>,
+        /// Free balance up to which an account can still be closed, swept to the holder
+        pub dust_limit: u128,
+        /// Maximum number of items visited per batch call (transfers in a batch transfer,
+        /// orders or ledgers in a bounded bank-wide operation)
+        pub max_batch_size: u16,
+        /// Allowances (epoch, owner, spender) -> amount the spender can transfer from
+        /// the owner
+        pub allowances: Allowances,
+        /// Allowance epoch, a reset moves to a new epoch to clear every allowance
+        pub allowance_epoch: u32,
+        /// Standing orders
+        pub standing_orders: Mapping<u32, StandingOrder>,
+        /// Standing order ids of every paying account
+        pub orders_by_owner: Mapping<AccountId, Vec<u32>>,
+        /// Next standing order id
+        pub next_order_id: u32,
+        /// First standing order id, orders below were cleared by a reset
+        pub first_order_id: u32,
+        /// Open escrows
+        pub escrows: Mapping<u32, Escrow>,
+        /// Open escrow ids of every payer
+        pub escrows_by_payer: Mapping<AccountId, Vec<u32>>,
+        /// Next escrow id
+        pub next_escrow_id: u32,
+        /// First escrow id, escrows below were cleared by a reset
+        pub first_escrow_id: u32,
+        /// Bank loans (epoch, account) -> loan
+        pub loans: Mapping<(u32, AccountId), Loan>,
+        /// Loan account index (position -> account) used for bank-wide iteration
+        pub loan_accounts: Mapping<u32, AccountId>,
+        /// Loan account positions (epoch, account) -> position used for removal
+        pub loan_positions: Mapping<(u32, AccountId), u32>,
+        /// Number of active loans
+        pub loan_count: u32,
+        /// Liquidation treasury (collateral seized from liquidated loans)
+        pub treasury: u128,
+        /// Bad debt (loan balances not covered by the seized collateral)
+        pub bad_debt: u128,
+        /// Role assignments (granted by the owner)
+        pub roles: Mapping<(Role, AccountId), ()>,
+        /// Timelock delay in blocks for sensitive parameter changes
+        pub timelock_delay: BlockNumber,
+        /// Pending time-locked changes
+        pub pending_changes: Lazy<Vec<PendingChange>>,
+        /// Next time-locked change id
+        pub next_change_id: u32,
+        /// Multisig approvers
+        pub approvers: Lazy<Vec<AccountId>>,
+        /// Number of approvals needed to execute a proposal (0 disables the multisig)
+        pub approval_threshold: u8,
+        /// Withdraw, credit and loan amounts above this need the approval of the approvers
+        pub approval_amount: u128,
+        /// Number of blocks a proposal stays open for approval
+        pub proposal_lifetime: BlockNumber,
+        /// Multisig proposals
+        pub proposals: Mapping<u32, Proposal>,
+        /// Next proposal id
+        pub next_proposal_id: u32,
+        /// Block of the pending destructive reset request
+        pub reset_requested_block: Option<BlockNumber>,
+        /// Emit error events (opt-in audit).  Failed messages revert, so these events
+        /// only show up in dry-run results.
+        pub emit_errors: bool,
         /// Status (0-Open, 1-Close)
         pub status: u8,
     }
@@ -135,10 +897,63 @@ mod bank {
                 loan_asset_id: loan_asset_id,
                 owner: caller,
                 manager: caller,
+                pending_owner: None,
+                pending_manager: None,
                 maximum_accounts: maximum_accounts,
                 threshold: threshold,
-                ledgers: Vec::new(),
-                loans: Vec::new(),
+                ledgers: Mapping::default(),
+                ledger_accounts: Mapping::default(),
+                ledger_positions: Mapping::default(),
+                ledger_count: 0,
+                ledger_epoch: 0,
+                total_balances: 0,
+                adb_checkpoints: Mapping::default(),
+                current_period: 0,
+                period_start_block: 0,
+                period_end_blocks: Mapping::default(),
+                period_end_days: Mapping::default(),
+                statements: Mapping::default(),
+                rate_versions: Mapping::default(),
+                rate_schedules: Mapping::default(),
+                interest_days: 0,
+                interest_days_block: Self::env().block_number(),
+                interest_pool: 0,
+                interest_pool_dust: 0,
+                interest_owed: 0,
+                interest_distribution: None,
+                distribution_balances: Mapping::default(),
+                next_distribution_id: 0,
+                closed_accounts: Mapping::default(),
+                dust_limit: 0,
+                max_batch_size: DEFAULT_MAX_BATCH_SIZE,
+                allowances: Mapping::default(),
+                allowance_epoch: 0,
+                standing_orders: Mapping::default(),
+                orders_by_owner: Mapping::default(),
+                next_order_id: 0,
+                first_order_id: 0,
+                escrows: Mapping::default(),
+                escrows_by_payer: Mapping::default(),
+                next_escrow_id: 0,
+                first_escrow_id: 0,
+                loans: Mapping::default(),
+                loan_accounts: Mapping::default(),
+                loan_positions: Mapping::default(),
+                loan_count: 0,
+                treasury: 0,
+                bad_debt: 0,
+                roles: Mapping::default(),
+                timelock_delay: daily_blocks.into(),
+                pending_changes: Lazy::default(),
+                next_change_id: 0,
+                approvers: Lazy::default(),
+                approval_threshold: 0,
+                approval_amount: 0,
+                proposal_lifetime: daily_blocks.into(),
+                proposals: Mapping::default(),
+                next_proposal_id: 0,
+                reset_requested_block: None,
+                emit_errors: false,
                 daily_blocks: daily_blocks,
                 status: 0u8,
             }
@@ -150,35 +965,35 @@ mod bank {
             Self::new(0u128, 0u128, 0u16, 0u16, 1u16)
         }
 
-        /// Setup bank
+        /// Setup bank.  Only allowed while the bank has no accounts and no loans.
         #[ink(message)]
+        #[allow(clippy::too_many_arguments)]
         pub fn setup(&mut self,
             asset_id: u128,
             loan_asset_id: u128,
             manager: AccountId,
             maximum_accounts: u16,
             threshold: u16,
-            daily_blocks: u16) -> Result<(), Error> {
+            daily_blocks: u16,
+            emit_errors: bool) -> Result<(), Error> {
             
             // Setup can only be done by the owner
             let caller = self.env().caller();
-            if self.env().caller() != self.owner {
-                self.env().emit_event(BankingEvent {
-                    operator: caller,
-                    status: BankTransactionStatus::EmitError(Error::BadOrigin),
-                });
-                return Ok(());
-            } 
+            self.ensure_owner()?;
 
-            // The setup will delete all existing accounts - Very Important!
-            self.asset_id = asset_id;
-            self.loan_asset_id = loan_asset_id;
+            // Setup never deletes accounts, it is only allowed on an empty bank.  Use
+            // the update messages to reconfigure a bank with accounts.
+            if self.ledger_count > 0 || self.loan_count > 0 {
+                return Err(self.fail(Error::BankNotEmpty));
+            }
+
+            self.set_asset_id(asset_id)?;
+            self.set_loan_asset_id(loan_asset_id)?;
+            self.set_maximum_accounts(maximum_accounts)?;
+            self.set_threshold(threshold)?;
+            self.set_daily_blocks(daily_blocks)?;
             self.manager = manager;
-            self.maximum_accounts = maximum_accounts;
-            self.threshold = threshold;
-            self.ledgers =  Vec::new();
-            self.loans =  Vec::new();
-            self.daily_blocks = daily_blocks;
+            self.emit_errors = emit_errors;
             self.status = 0;
 
             self.env().emit_event(BankingEvent {
@@ -203,748 +1018,4454 @@ mod bank {
             )
         }
 
-        /// Close the bank
+        /// Update the bank asset.  Not allowed while any ledger or the treasury holds
+        /// a balance.
         #[ink(message)]
-        pub fn close(&mut self) -> Result<(), Error> {
+        pub fn update_asset_id(&mut self,
+            asset_id: u128) -> Result<(), Error> {
 
-            // Closing the can only be done by the manager
-            let caller = self.env().caller();
-            if self.env().caller() != self.manager {
-                self.env().emit_event(BankingEvent {
-                    operator: caller,
-                    status: BankTransactionStatus::EmitError(Error::BadOrigin),
-                });
-                return Ok(());
-            } 
+            self.ensure_owner()?;
+            self.set_asset_id(asset_id)
+        }
 
-            // This will close the bank
-            self.status = 1;
+        /// Update the bank loan asset.  Not allowed while there are active loans.
+        #[ink(message)]
+        pub fn update_loan_asset_id(&mut self,
+            loan_asset_id: u128) -> Result<(), Error> {
 
-            self.env().emit_event(BankingEvent {
-                operator: caller,
-                status: BankTransactionStatus::EmitSuccess(Success::BankCloseSuccess),
-            });
+            self.ensure_owner()?;
+            self.set_loan_asset_id(loan_asset_id)
+        }
+
+        /// Update the maximum accounts.  Cannot go below the current number of accounts.
+        #[ink(message)]
+        pub fn update_maximum_accounts(&mut self,
+            maximum_accounts: u16) -> Result<(), Error> {
+
+            self.ensure_owner()?;
+            self.set_maximum_accounts(maximum_accounts)
+        }
+
+        /// Update the dust limit: the free balance up to which an account can still be
+        /// closed.
+        #[ink(message)]
+        pub fn update_dust_limit(&mut self,
+            dust_limit: u128) -> Result<(), Error> {
+
+            self.ensure_owner()?;
+
+            let old_value = self.dust_limit;
+            self.dust_limit = dust_limit;
+            self.emit_parameter_changed(Parameter::DustLimit, old_value, dust_limit);
 
             Ok(())
         }
 
-        /// Open the bank
+        /// Update the maximum number of transfers in a batch transfer (must be greater
+        /// than zero)
         #[ink(message)]
-        pub fn open(&mut self) -> Result<(), Error> {
+        pub fn update_max_batch_size(&mut self,
+            max_batch_size: u16) -> Result<(), Error> {
 
-            // Opening the can only be done by the manager
-            let caller = self.env().caller();
-            if self.env().caller() != self.manager {
-                self.env().emit_event(BankingEvent {
-                    operator: caller,
-                    status: BankTransactionStatus::EmitError(Error::BadOrigin),
-                });
-                return Ok(());
-            } 
+            self.ensure_owner()?;
 
-            // This will open the bank
-            self.status = 0;
+            if max_batch_size == 0 {
+                return Err(self.fail(Error::InvalidParameter));
+            }
 
-            self.env().emit_event(BankingEvent {
-                operator: caller,
-                status: BankTransactionStatus::EmitSuccess(Success::BankOpenSuccess),
-            });
+            let old_value = self.max_batch_size;
+            self.max_batch_size = max_batch_size;
+            self.emit_parameter_changed(Parameter::MaxBatchSize, old_value.into(), max_batch_size.into());
 
             Ok(())
-        }        
+        }
 
-        /// Deposit to the bank
+        /// Enable or disable the error events (opt-in audit)
         #[ink(message)]
-        pub fn deposit(&mut self,
-            account: AccountId,
-            amount: u128) -> Result<(), Error> {
+        pub fn update_emit_errors(&mut self,
+            emit_errors: bool) -> Result<(), Error> {
 
-            let current_block = self.env().block_number() as u128;
+            self.ensure_owner()?;
+
+            let old_value = self.emit_errors;
+            self.emit_errors = emit_errors;
+            self.emit_parameter_changed(Parameter::EmitErrors, old_value.into(), emit_errors.into());
+
+            Ok(())
+        }
+
+        /// Queue a time-locked change.  Threshold and daily blocks changes can be queued
+        /// by the owner or the manager, manager and timelock delay changes by the owner
+        /// only.  Returns the change id.
+        #[ink(message)]
+        pub fn queue_change(&mut self,
+            change: TimelockedChange) -> Result<u32, Error> {
 
-            // Deposit can only be done by the manager once the transfer of the 
-            // asset is verified through the tx-hash.
             let caller = self.env().caller();
-            if self.env().caller() != self.manager {
-                self.env().emit_event(BankingEvent {
-                    operator: caller,
-                    status: BankTransactionStatus::EmitError(Error::BadOrigin),
-                });
-                return Ok(());
-            } 
+            match change {
+                TimelockedChange::Threshold(_) | TimelockedChange::DailyBlocks(_) => {
+                    if caller != self.owner && caller != self.manager {
+                        return Err(self.fail(Error::BadOrigin));
+                    }
+                },
+                TimelockedChange::Manager(_) | TimelockedChange::TimelockDelay(_) => {
+                    self.ensure_owner()?;
+                },
+            }
 
-            // Check if the bank is open
-            if self.status != 0 {
-                self.env().emit_event(BankingEvent {
-                    operator: caller,
-                    status: BankTransactionStatus::EmitError(Error::BankIsClose),
-                });
-                return Ok(());
+            // Validate upfront, the value is validated again on execution
+            match change {
+                TimelockedChange::Threshold(threshold) if threshold > 100 => {
+                    return Err(self.fail(Error::InvalidParameter));
+                },
+                TimelockedChange::DailyBlocks(0) => {
+                    return Err(self.fail(Error::InvalidParameter));
+                },
+                _ => {},
             }
 
-            // Search if the account exist already, if it does in just add to the
-            // ledger the amount deposited, if not then create the new account.
-            // 1. Update a balance
-            let mut account_found = false;
-            for ledger in self.ledgers.iter_mut() {
-                if ledger.account == account {
-                    
-                    ledger.balance = ledger
-                        .balance
-                        .checked_add(amount)
-                        .ok_or(Error::AccountBalanceOverflow)?; 
-
-                    // ADB computation
-                    let blocks_elapsed = current_block
-                        .saturating_sub(ledger.adb_beginning_block);
-
-                    ledger.adb = ledger.balance
-                        .checked_mul(blocks_elapsed)
-                        .ok_or(Error::AccountBalanceOverflow)?
-                        .checked_div(self.daily_blocks.into())
-                        .unwrap_or(0);
-
-                    account_found = true;
-                    break;
-                }
+            // Capped per proposer, so the manager cannot block the changes of the owner
+            let mut pending_changes = self.pending_changes.get_or_default();
+            let proposed = pending_changes.iter()
+                .filter(|pending| pending.proposer == caller)
+                .count();
+            if proposed >= MAXIMUM_PENDING_CHANGES {
+                return Err(self.fail(Error::TooManyPendingChanges));
+            }
+
+            let id = self.next_change_id;
+            let eta = self.env().block_number()
+                .checked_add(self.timelock_delay)
+                .ok_or_else(|| self.fail(Error::InvalidParameter))?;
+
+            pending_changes.push(PendingChange {
+                id,
+                change: change.clone(),
+                proposer: caller,
+                eta,
+            });
+            self.pending_changes.set(&pending_changes);
+            self.next_change_id = id.wrapping_add(1);
+
+            self.env().emit_event(ChangeQueued {
+                id,
+                change,
+                eta,
+            });
+
+            Ok(id)
+        }
+
+        /// Execute a queued change once its delay has passed.  Anyone can execute it.
+        #[ink(message)]
+        pub fn execute_change(&mut self,
+            id: u32) -> Result<(), Error> {
+
+            let mut pending_changes = self.pending_changes.get_or_default();
+            let position = pending_changes.iter()
+                .position(|c| c.id == id)
+                .ok_or_else(|| self.fail(Error::ChangeNotFound))?;
+
+            if self.env().block_number() < pending_changes[position].eta {
+                return Err(self.fail(Error::TimelockNotExpired));
             }
-            // 2. Create a new account if the account does not exist
-            if !account_found {
-                if self.ledgers.len() as u16 >= self.maximum_accounts {
-                    self.env().emit_event(BankingEvent {
-                        operator: caller,
-                        status: BankTransactionStatus::EmitError(Error::BankAccountMaxOut),
+
+            let pending_change = pending_changes.remove(position);
+            self.pending_changes.set(&pending_changes);
+
+            match pending_change.change {
+                TimelockedChange::Threshold(threshold) => self.set_threshold(threshold)?,
+                TimelockedChange::DailyBlocks(daily_blocks) => self.set_daily_blocks(daily_blocks)?,
+                TimelockedChange::Manager(new_manager) => {
+                    // The new manager still has to accept
+                    self.pending_manager = Some(new_manager);
+                    self.env().emit_event(ManagerProposed {
+                        previous_manager: self.manager,
+                        new_manager,
                     });
-                    return Ok(());
-                }
-                let new_ledger = Ledger {
-                    account,
-                    balance: amount,
-                    adb: amount,
-                    adb_beginning_block: current_block,
-                    status: 1, // 1 = Liquid
-                };
-                self.ledgers.push(new_ledger);
+                },
+                TimelockedChange::TimelockDelay(timelock_delay) => {
+                    self.timelock_delay = timelock_delay;
+                },
             }
 
+            self.env().emit_event(ChangeExecuted {
+                id,
+            });
+
+            Ok(())
+        }
+
+        /// Cancel a queued change.  Only the owner can cancel.
+        #[ink(message)]
+        pub fn cancel_change(&mut self,
+            id: u32) -> Result<(), Error> {
+
+            self.ensure_owner()?;
+
+            let mut pending_changes = self.pending_changes.get_or_default();
+            let position = pending_changes.iter()
+                .position(|c| c.id == id)
+                .ok_or_else(|| self.fail(Error::ChangeNotFound))?;
+            pending_changes.remove(position);
+            self.pending_changes.set(&pending_changes);
+
+            self.env().emit_event(ChangeCancelled {
+                id,
+            });
+
+            Ok(())
+        }
+
+        /// Get the pending time-locked changes
+        #[ink(message)]
+        pub fn get_pending_changes(&self) -> Vec<PendingChange> {
+            self.pending_changes.get_or_default()
+        }
+
+        /// Get the timelock delay in blocks
+        #[ink(message)]
+        pub fn get_timelock_delay(&self) -> BlockNumber {
+            self.timelock_delay
+        }
+
+        /// Request a destructive reset of all ledgers and loans.  The reset must be
+        /// confirmed with `confirm_reset` in a later block.
+        #[ink(message)]
+        pub fn request_reset(&mut self) -> Result<(), Error> {
+
+            let caller = self.env().caller();
+            self.ensure_owner()?;
+
+            self.reset_requested_block = Some(self.env().block_number());
+
             self.env().emit_event(BankingEvent {
                 operator: caller,
-                status: BankTransactionStatus::EmitSuccess(Success::AccountDepositSuccess),
+                status: BankTransactionStatus::EmitSuccess(Success::BankResetRequestSuccess),
             });
 
             Ok(())
         }
 
-        /// Withdraw from the bank
+        /// Cancel a requested reset
         #[ink(message)]
-        pub fn withdraw(&mut self,
-            account: AccountId,
-            amount: u128) -> Result<(), ContractError> {
+        pub fn cancel_reset(&mut self) -> Result<(), Error> {
 
-            let current_block = self.env().block_number() as u128;
-
-            // Withdraw can only be done by the manager once the balance of the account
-            // is sufficient for withdrawal
             let caller = self.env().caller();
-            if self.env().caller() != self.manager {
-                self.env().emit_event(BankingEvent {
-                    operator: caller,
-                    status: BankTransactionStatus::EmitError(Error::BadOrigin),
-                });
-                return Ok(());
-            } 
+            self.ensure_owner()?;
 
-            // Check if the bank is open
-            if self.status != 0 {
-                self.env().emit_event(BankingEvent {
-                    operator: caller,
-                    status: BankTransactionStatus::EmitError(Error::BankIsClose),
-                });
-                return Ok(());
+            if self.reset_requested_block.take().is_none() {
+                return Err(self.fail(Error::ResetNotRequested));
             }
 
-            // Search if the account exist already, if it does, check if the balance is
-            // sufficient, if so, deduct the ledger, if not raise a balance insufficient
-            // error.
-            let mut account_found = false;
-            for ledger in self.ledgers.iter_mut() {
-                if ledger.account == account {
-                    account_found = true;
-
-                    // Check if balance is sufficient
-                    if ledger.balance < amount {
-                        self.env().emit_event(BankingEvent {
-                            operator: caller,
-                            status: BankTransactionStatus::EmitError(Error::AccountBalanceInsufficient),
-                        });
-                        return Ok(());
-                    }
-
-                    // Deduct the amount
-                    ledger.balance -= amount;
+            self.env().emit_event(BankingEvent {
+                operator: caller,
+                status: BankTransactionStatus::EmitSuccess(Success::BankResetCancelSuccess),
+            });
 
-                    // ADB computation
-                    let blocks_elapsed = current_block
-                        .saturating_sub(ledger.adb_beginning_block);
+            Ok(())
+        }
 
-                    ledger.adb = ledger.balance
-                        .checked_mul(blocks_elapsed)
-                        .ok_or(Error::AccountBalanceOverflow)?
-                        .checked_div(self.daily_blocks.into())
-                        .unwrap_or(0);                    
+        /// Confirm a requested reset.  This will delete all existing accounts, loans,
+        /// escrows, standing orders and allowances - Very Important!
+        #[ink(message)]
+        pub fn confirm_reset(&mut self) -> Result<(), Error> {
 
-                    // Transfer the asset to the account
-                    self.env()
-                        .call_runtime(&RuntimeCall::Assets(AssetsCall::Transfer {
-                            id: self.asset_id,
-                            target: account.into(),
-                            amount: amount,
-                        }))
-                        .map_err(|_| RuntimeError::CallRuntimeFailed)?;
+            let caller = self.env().caller();
+            self.ensure_owner()?;
 
-                    break;
-                }
+            // The confirmation must come in a later block than the request
+            let requested_block = self.reset_requested_block
+                .ok_or_else(|| self.fail(Error::ResetNotRequested))?;
+            if self.env().block_number() <= requested_block {
+                return Err(self.fail(Error::ResetNotRequested));
             }
 
-            if !account_found {
-                self.env().emit_event(BankingEvent {
-                    operator: caller,
-                    status: BankTransactionStatus::EmitError(Error::AccountNotFound),
-                });
-                return Ok(());
-            }
+            // Ledgers, loans, escrows, standing orders and allowances are cleared without
+            // iterating them: the previous epochs and older ids are no longer found
+            self.ledger_epoch = self.ledger_epoch.wrapping_add(1);
+            self.ledger_count = 0;
+            self.loan_count = 0;
+            self.total_balances = 0;
+            self.first_escrow_id = self.next_escrow_id;
+            self.first_order_id = self.next_order_id;
+            self.allowance_epoch = self.allowance_epoch.wrapping_add(1);
+
+            self.treasury = 0;
+            self.bad_debt = 0;
+            self.interest_pool = 0;
+            self.interest_pool_dust = 0;
+            self.interest_owed = 0;
+            self.interest_distribution = None;
+            self.reset_requested_block = None;
 
             self.env().emit_event(BankingEvent {
                 operator: caller,
-                status: BankTransactionStatus::EmitSuccess(Success::AccountWithdrawalSuccess),
+                status: BankTransactionStatus::EmitSuccess(Success::BankResetSuccess),
             });
 
             Ok(())
         }
 
-        /// Credit to the account (add).  This is done by the manager only.
+        /// Close the bank
         #[ink(message)]
-        pub fn credit(&mut self,
-            account: AccountId,
-            amount: u128) -> Result<(), Error> {
-            
-            let current_block = self.env().block_number() as u128;
+        pub fn close(&mut self) -> Result<(), Error> {
 
-            // Credit is adding to the balance of an account, this is done only
-            // by the manager.
+            // Closing the bank can only be done by a pauser
             let caller = self.env().caller();
+            self.ensure_role(Role::Pauser)?;
+
+            // This will close the bank
+            self.status = 1;
+
+            self.env().emit_event(BankingEvent {
+                operator: caller,
+                status: BankTransactionStatus::EmitSuccess(Success::BankCloseSuccess),
+            });
+
+            Ok(())
+        }
+
+        /// Open the bank
+        #[ink(message)]
+        pub fn open(&mut self) -> Result<(), Error> {
 
+            // Opening the can only be done by the manager
+            let caller = self.env().caller();
             if self.env().caller() != self.manager {
-                self.env().emit_event(BankingEvent {
-                    operator: caller,
-                    status: BankTransactionStatus::EmitError(Error::BadOrigin),
-                });
-                return Ok(());
+                return Err(self.fail(Error::BadOrigin));
             } 
 
-            // Check if the bank is open
-            if self.status != 0 {
-                self.env().emit_event(BankingEvent {
-                    operator: caller,
-                    status: BankTransactionStatus::EmitError(Error::BankIsClose),
-                });
-                return Ok(());
-            }
+            // This will open the bank
+            self.status = 0;
 
-            // Search for the caller account in the ledger, if found, add to the balance
-            // the given amount.
-            let mut account_found = false;
-
-            for ledger in self.ledgers.iter_mut() {
-                if ledger.account == account {
-                    account_found = true;
-
-                    // Check if account is liquid
-                    if ledger.status != 1 {
-                        self.env().emit_event(BankingEvent {
-                            operator: caller,
-                            status: BankTransactionStatus::EmitError(Error::AccountFrozen),
-                        });
-                        return Ok(());
-                    }
+            self.env().emit_event(BankingEvent {
+                operator: caller,
+                status: BankTransactionStatus::EmitSuccess(Success::BankOpenSuccess),
+            });
 
-                    // Add the amount to the balance safely
-                    match ledger.balance.checked_add(amount) {
-                        Some(new_balance) => {
-                            ledger.balance = new_balance;
+            Ok(())
+        }        
 
-                            // ADB computation
-                            let blocks_elapsed = current_block
-                                .saturating_sub(ledger.adb_beginning_block);
+        /// Deposit to the bank.  Manager path kept for legacy reconciliation, use
+        /// `self_deposit` for trustless deposits.  Returns the proposal id if the
+        /// deposit needs the approval of the approvers.
+        #[ink(message)]
+        pub fn deposit(&mut self,
+            account: AccountId,
+            amount: u128) -> Result<Option<u32>, Error> {
 
-                            ledger.adb = ledger.balance
-                                .checked_mul(blocks_elapsed)
-                                .ok_or(Error::AccountBalanceOverflow)?
-                                .checked_div(self.daily_blocks.into())
-                                .unwrap_or(0);  
-                        },
-                        None => {
-                            self.env().emit_event(BankingEvent {
-                                operator: caller,
-                                status: BankTransactionStatus::EmitError(Error::AccountBalanceOverflow),
-                            });
-                            return Ok(());
-                        }
-                    }
+            // Deposit can only be done by a teller once the transfer of the 
+            // asset is verified through the tx-hash.
+            self.ensure_role(Role::Teller)?;
 
-                    break;
+            // High-value actions need the approval of the approvers
+            if self.requires_approval(amount) {
+                if let Some(id) = self.create_proposal(ProposalAction::Deposit { account, amount })? {
+                    return Ok(Some(id));
                 }
             }
 
-            if !account_found {
-                self.env().emit_event(BankingEvent {
-                    operator: caller,
-                    status: BankTransactionStatus::EmitError(Error::AccountNotFound),
-                });
-                return Ok(());
+            self.execute_deposit(account, amount)?;
+
+            Ok(None)
+        }
+
+        /// Open an account, by the account holder (self-service) or by a teller
+        #[ink(message)]
+        pub fn open_account(&mut self,
+            account: AccountId,
+            metadata: Option<Hash>) -> Result<(), Error> {
+
+            let caller = self.env().caller();
+            if caller != account {
+                self.ensure_role(Role::Teller)?;
+            }
+
+            // Check if the bank is open
+            if self.status != 0 {
+                return Err(self.fail(Error::BankIsClose));
+            }
+
+            if self.ledgers.contains((self.ledger_epoch, account)) {
+                return Err(self.fail(Error::AccountAlreadyExist));
             }
 
+            self.create_ledger(account, metadata)?;
+
             self.env().emit_event(BankingEvent {
                 operator: caller,
-                status: BankTransactionStatus::EmitSuccess(Success::AccountCreditSuccess),
+                status: BankTransactionStatus::EmitSuccess(Success::AccountOpenSuccess),
             });
 
             Ok(())
         }
 
-        /// Debit to the account (deduct).  This is done by any depositor.
+        /// Close an account, by the account holder or by a teller.  The account must
+        /// have no loan, no locked balance and a free balance not above the dust limit.
+        /// The dust is swept to the holder, the ledger slot is freed and a tombstone is
+        /// kept for audit.
         #[ink(message)]
-        pub fn debit(&mut self,
-            amount: u128) -> Result<(), Error> {
+        pub fn close_account(&mut self,
+            account: AccountId) -> Result<(), ContractError> {
 
-            let current_block = self.env().block_number() as u128;
             let caller = self.env().caller();
+            if caller != account {
+                self.ensure_role(Role::Teller)?;
+            }
 
             // Check if the bank is open
             if self.status != 0 {
-                self.env().emit_event(BankingEvent {
-                    operator: caller,
-                    status: BankTransactionStatus::EmitError(Error::BankIsClose),
-                });
-                return Ok(());
+                return Err(self.fail(Error::BankIsClose).into());
             }
 
-            // Search for the caller account in the ledger
-            let mut account_found = false;
-
-            for ledger in self.ledgers.iter_mut() {
-                if ledger.account == caller {
-                    account_found = true;
-
-                    // Check if account is liquid
-                    if ledger.status != 1 {
-                        self.env().emit_event(BankingEvent {
-                            operator: caller,
-                            status: BankTransactionStatus::EmitError(Error::AccountFrozen),
-                        });
-                        return Ok(());
-                    }
-
-                    // Check if balance is sufficient
-                    if ledger.balance < amount {
-                        self.env().emit_event(BankingEvent {
-                            operator: caller,
-                            status: BankTransactionStatus::EmitError(Error::AccountBalanceInsufficient),
-                        });
-                        return Ok(());
-                    }
+            // The ledger index must not move while a distribution walks it
+            if self.interest_distribution.is_some() {
+                return Err(self.fail(Error::DistributionInProgress).into());
+            }
 
-                    ledger.balance -= amount;
+            let ledger = self.load_ledger(account)?;
 
-                    // ADB computation
-                    let blocks_elapsed = current_block
-                        .saturating_sub(ledger.adb_beginning_block);
+            // Frozen accounts cannot be closed
+            if ledger.status == AccountStatus::Frozen {
+                return Err(self.fail(Error::AccountFrozen).into());
+            }
 
-                    ledger.adb = ledger.balance
-                        .checked_mul(blocks_elapsed)
-                        .ok_or(Error::AccountBalanceOverflow)?
-                        .checked_div(self.daily_blocks.into())
-                        .unwrap_or(0); 
+            if self.loans.contains((self.ledger_epoch, account)) {
+                return Err(self.fail(Error::AccountHasLoan).into());
+            }
 
-                    break;
-                }
+            if ledger.locked > 0 || ledger.deferred_interest > 0 || ledger.balance > self.dust_limit {
+                return Err(self.fail(Error::AccountNotEmpty).into());
             }
 
-            // Account not found
-            if !account_found {
-                self.env().emit_event(BankingEvent {
-                    operator: caller,
-                    status: BankTransactionStatus::EmitError(Error::AccountNotFound),
-                });
-                return Ok(());
+            // Free the ledger slot and keep a tombstone
+            self.total_balances = self.total_balances
+                .checked_sub(ledger.balance)
+                .ok_or_else(|| self.fail(Error::AccountBalanceOverflow))?;
+            self.ledgers.remove((self.ledger_epoch, account));
+            self.remove_ledger_index(account);
+            self.closed_accounts.insert(account, &ClosedAccount {
+                account,
+                metadata: ledger.metadata,
+                opened_block: ledger.adb_beginning_block,
+                closed_block: self.env().block_number() as u128,
+                closed_by: caller,
+                swept: ledger.balance,
+            });
+
+            // Sweep the dust to the holder
+            if ledger.balance > 0 {
+                self.env()
+                    .call_runtime(&RuntimeCall::Assets(AssetsCall::Transfer {
+                        id: self.asset_id,
+                        target: account.into(),
+                        amount: ledger.balance,
+                    }))
+                    .map_err(|_| RuntimeError::CallRuntimeFailed)?;
             }
 
+            self.env().emit_event(AccountStatusChanged {
+                account,
+                from: ledger.status,
+                to: AccountStatus::Closed,
+                reason: 0,
+            });
+
             self.env().emit_event(BankingEvent {
                 operator: caller,
-                status: BankTransactionStatus::EmitSuccess(Success::AccountDebitSuccess),
+                status: BankTransactionStatus::EmitSuccess(Success::AccountCloseSuccess),
             });
 
             Ok(())
         }
 
-        /// Credit interest.  Interest are computed off-chain and needs a manual
-        /// transfer to the bank smart contract. 
+        /// Get the tombstone of a closed account
         #[ink(message)]
-        pub fn credit_interest(&mut self,
-            rate: u128) -> Result<(), Error> {
+        pub fn get_closed_account(&self,
+            account: AccountId) -> Option<ClosedAccount> {
 
-            let current_block = self.env().block_number() as u128;
+            self.closed_accounts.get(account)
+        }
 
-            // Credit is adding to the balance of an account, this is done only
-            // by the manager.
-            let caller = self.env().caller();
+        /// Self-service deposit.  The depositor must first approve the bank contract
+        /// to spend the amount through `Assets::approve_transfer`.  The bank pulls the
+        /// approved asset and credits the depositor's ledger only if the transfer
+        /// succeeds.
+        #[ink(message)]
+        pub fn self_deposit(&mut self,
+            amount: u128) -> Result<(), ContractError> {
 
-            if self.env().caller() != self.manager {
-                self.env().emit_event(BankingEvent {
-                    operator: caller,
-                    status: BankTransactionStatus::EmitError(Error::BadOrigin),
-                });
-                return Ok(());
-            } 
+            let caller = self.env().caller();
 
             // Check if the bank is open
             if self.status != 0 {
-                self.env().emit_event(BankingEvent {
-                    operator: caller,
-                    status: BankTransactionStatus::EmitError(Error::BankIsClose),
-                });
-                return Ok(());
+                return Err(self.fail(Error::BankIsClose).into());
             }
 
-            for ledger in self.ledgers.iter_mut() {
-                if ledger.status == 0 {
-                    // Compute interest: interest = adb * rate / 100
-                    let interest = ledger.adb
-                        .checked_mul(rate)
-                        .ok_or(Error::AccountBalanceOverflow)?
-                        .checked_div(100)
-                        .unwrap_or(0);
-
-                    // Credit interest to balance
-                    ledger.balance = ledger.balance
-                        .checked_add(interest)
-                        .ok_or(Error::AccountBalanceOverflow)?;
-
-                    // ADB computation
-                    let blocks_elapsed = current_block
-                        .saturating_sub(ledger.adb_beginning_block);
+            // Pull the approved asset from the depositor into the bank contract
+            self.env()
+                .call_runtime(&RuntimeCall::Assets(AssetsCall::TransferApproved {
+                    id: self.asset_id,
+                    owner: caller.into(),
+                    destination: self.env().account_id().into(),
+                    amount,
+                }))
+                .map_err(|_| RuntimeError::CallRuntimeFailed)?;
 
-                    ledger.adb = ledger.balance
-                        .checked_mul(blocks_elapsed)
-                        .ok_or(Error::AccountBalanceOverflow)?
-                        .checked_div(self.daily_blocks.into())
-                        .unwrap_or(0);
-                }
-            }
+            // The asset has arrived, credit the ledger.  An error here reverts the
+            // transfer as well.
+            self.deposit_to_ledger(caller, amount)?;
 
             self.env().emit_event(BankingEvent {
                 operator: caller,
-                status: BankTransactionStatus::EmitSuccess(Success::AccountCreditSuccess),
+                status: BankTransactionStatus::EmitSuccess(Success::AccountDepositSuccess),
             });
 
             Ok(())
         }
 
-        /// Apply for a loan
-        /// For example: loan_amount (encoded) = $100 USDT
-        ///              price (oracle) = $0.01
-        ///              collateral (encoded) = 11,000 
-        ///              threshold (setup) = 5% (Upon liquidation the value must be $105)
-        ///              liquidation_price (computed) = $105 / 11,000 = $0.00954545454
-        /// Rules:
-        ///     1. The account must have a balance greater than the collateral.
-        ///     2. The collateral must be within the threshold.
-        ///     3. To have an acceptable liquidation_price the collateral must take into consideration the 
-        ///        volatility of the asset price or else the loan will immediately liquidated.
+        /// Withdraw from the bank.  Manager path for assisted withdrawals, account
+        /// holders use `self_withdraw`.  Returns the proposal id if the withdrawal
+        /// needs the approval of the approvers.
         #[ink(message)]
-        pub fn loan_application(&mut self,
+        pub fn withdraw(&mut self,
             account: AccountId,
-            loan_amount: u128,
-            price: u128,
-            collateral: u128) -> Result<(), Error> {
+            amount: u128) -> Result<Option<u32>, ContractError> {
+
+            // Withdraw can only be done by a teller once the balance of the account
+            // is sufficient for withdrawal
+            self.ensure_role(Role::Teller)?;
+
+            // High-value actions need the approval of the approvers
+            if self.requires_approval(amount) {
+                if let Some(id) = self.create_proposal(ProposalAction::Withdraw { account, amount })? {
+                    return Ok(Some(id));
+                }
+            }
+
+            self.execute_withdraw(account, amount)?;
+
+            Ok(None)
+        }
+
+        /// Self-service withdrawal.  The account holder withdraws from their own
+        /// ledger to their own address.
+        #[ink(message)]
+        pub fn self_withdraw(&mut self,
+            amount: u128) -> Result<(), ContractError> {
 
-            // Loan application can only be called by the manager due to oracle input
             let caller = self.env().caller();
-            if self.env().caller() != self.manager {
-                self.env().emit_event(BankingEvent {
-                    operator: caller,
-                    status: BankTransactionStatus::EmitError(Error::BadOrigin),
-                });
-                return Ok(());
-            } 
 
             // Check if the bank is open
             if self.status != 0 {
-                self.env().emit_event(BankingEvent {
-                    operator: caller,
-                    status: BankTransactionStatus::EmitError(Error::BankIsClose),
-                });
-                return Ok(());
-            } 
+                return Err(self.fail(Error::BankIsClose).into());
+            }
 
-            // Check if the account is a depositor
-            let ledger_index = self.ledgers.iter().position(|l| l.account == account);
-            let ledger_index = match ledger_index {
-                Some(i) => i,
-                None => {
-                    self.env().emit_event(BankingEvent {
-                        operator: caller,
-                        status: BankTransactionStatus::EmitError(Error::AccountNotFound),
-                    });
-                    return Ok(());
-                }
-            };
+            // Search for the caller account in the ledger
+            let mut ledger = self.load_ledger(caller)?;
 
-            // Check if the account is frozen
-            if self.ledgers[ledger_index].status == 1 {
-                self.env().emit_event(BankingEvent {
-                    operator: caller,
-                    status: BankTransactionStatus::EmitError(Error::AccountFrozen),
-                });
-                return Ok(());
-            }
+            // Check if account is active
+            self.ensure_active(&ledger)?;
 
-            // Check if the balance can cover the collateral
-            if collateral > self.ledgers[ledger_index].balance {
-                self.env().emit_event(BankingEvent {
-                    operator: caller,
-                    status: BankTransactionStatus::EmitError(Error::AccountBalanceInsufficient),
-                });
-                return Ok(());
+            // Check if balance is sufficient
+            if ledger.balance < amount {
+                return Err(self.fail(Error::AccountBalanceInsufficient).into());
             }
 
-            // Check if the collateral can cover the liquidation price
-            // 1. compute for the threshold price: current price plus the threshold.
-            // 2. use the threshold price to compute for the collateral value
-            let threshold_price = price
-                .checked_add(
-                    price
-                        .checked_mul(self.threshold.into())
-                        .ok_or(Error::LoanComputationOverflow)?
-                        .checked_div(100)
-                        .ok_or(Error::LoanComputationOverflow)?
-                )
-                .ok_or(Error::LoanComputationOverflow)?;
+            // Deduct the amount
+            let balance = ledger.balance - amount;
+            self.set_balance(&mut ledger, balance)?;
 
-            let collateral_value = collateral
-                .checked_mul(threshold_price)
-                .ok_or(Error::LoanComputationOverflow)?;
+            self.ledgers.insert((self.ledger_epoch, caller), &ledger);
 
-            if loan_amount > collateral_value {
-                self.env().emit_event(BankingEvent {
-                    operator: caller,
-                    status: BankTransactionStatus::EmitError(Error::LoanCollateralInsufficient),
-                });
-                return Ok(());
+            // Transfer the asset to the account holder
+            self.env()
+                .call_runtime(&RuntimeCall::Assets(AssetsCall::Transfer {
+                    id: self.asset_id,
+                    target: caller.into(),
+                    amount,
+                }))
+                .map_err(|_| RuntimeError::CallRuntimeFailed)?;
+
+            self.env().emit_event(BankingEvent {
+                operator: caller,
+                status: BankTransactionStatus::EmitSuccess(Success::AccountWithdrawalSuccess),
+            });
+
+            Ok(())
+        }
+
+        /// Credit to the account (add).  This is done by a teller only.  Returns the
+        /// proposal id if the credit needs the approval of the approvers.
+        #[ink(message)]
+        pub fn credit(&mut self,
+            account: AccountId,
+            amount: u128) -> Result<Option<u32>, Error> {
+            
+            // Credit is adding to the balance of an account, this is done only
+            // by a teller.
+            self.ensure_role(Role::Teller)?;
+
+            // High-value actions need the approval of the approvers
+            if self.requires_approval(amount) {
+                if let Some(id) = self.create_proposal(ProposalAction::Credit { account, amount })? {
+                    return Ok(Some(id));
+                }
             }
 
-            // Check if there is an existing loan, if there is none then add a loan
-            let loan_exists = self.loans.iter().any(|l| l.account == account);
-            if loan_exists {
-                self.env().emit_event(BankingEvent {
-                    operator: caller,
-                    status: BankTransactionStatus::EmitError(Error::LoanAlreadyExist),
-                });
-                return Ok(());
+            self.execute_credit(account, amount)?;
+
+            Ok(None)
+        }
+
+        /// Debit to the account (deduct).  This is done by any depositor.
+        #[ink(message)]
+        pub fn debit(&mut self,
+            amount: u128) -> Result<(), Error> {
+
+            let caller = self.env().caller();
+
+            // Check if the bank is open
+            if self.status != 0 {
+                return Err(self.fail(Error::BankIsClose));
             }
 
-            // Now add the loan, but first compute for the liquidation price.
-            //      liquidation_price = (loan + threshold) / collateral
-            let loan_with_threshold = loan_amount
-                .checked_add(
-                    loan_amount
-                        .checked_mul(self.threshold as u128)
-                        .ok_or(Error::LoanComputationOverflow)?
-                        .checked_div(100)
-                        .ok_or(Error::LoanComputationOverflow)?
-                )
-                .ok_or(Error::LoanComputationOverflow)?;
+            // Search for the caller account in the ledger
+            let mut ledger = self.load_ledger(caller)?;
 
-            let liquidation_price = loan_with_threshold
-                .checked_div(collateral)
-                .ok_or(Error::LoanComputationOverflow)?;
+            // Check if account is active
+            self.ensure_active(&ledger)?;
 
-            self.loans.push(Loan {
-                account,
-                collateral,
-                loan_amount,
-                paid_amount: 0,
-                balance: loan_amount,
-                liquidation_price,
-            });
+            // Check if balance is sufficient
+            if ledger.balance < amount {
+                return Err(self.fail(Error::AccountBalanceInsufficient));
+            }
+
+            let balance = ledger.balance - amount;
+            self.set_balance(&mut ledger, balance)?;
+
+            self.ledgers.insert((self.ledger_epoch, caller), &ledger);
 
-            // Success
             self.env().emit_event(BankingEvent {
                 operator: caller,
-                status: BankTransactionStatus::EmitSuccess(Success::LoanApplicationSuccess),
+                status: BankTransactionStatus::EmitSuccess(Success::AccountDebitSuccess),
             });
 
             Ok(())
         }
 
-        /// Pay loan
+        /// Transfer from the caller's ledger to another ledger in the bank.  No asset
+        /// leaves the bank.  The recipient account is opened if it does not exist and
+        /// `auto_open` is set.
         #[ink(message)]
-        pub fn loan_payment(&mut self,
-            account: AccountId,
-            amount: u128) -> Result<(), Error> {
+        pub fn transfer(&mut self,
+            to: AccountId,
+            amount: u128,
+            auto_open: bool) -> Result<(), Error> {
 
-            // Loan payment can only be called by the manager after accepting USDT transfer
             let caller = self.env().caller();
-            if self.env().caller() != self.manager {
-                self.env().emit_event(BankingEvent {
-                    operator: caller,
-                    status: BankTransactionStatus::EmitError(Error::BadOrigin),
-                });
-                return Ok(());
-            } 
 
             // Check if the bank is open
             if self.status != 0 {
-                self.env().emit_event(BankingEvent {
-                    operator: caller,
-                    status: BankTransactionStatus::EmitError(Error::BankIsClose),
-                });
-                return Ok(());
-            } 
+                return Err(self.fail(Error::BankIsClose));
+            }
 
-            // Search for the loan
-            let loan_index = match self.loans.iter().position(|l| l.account == account) {
-                Some(i) => i,
-                None => {
-                    self.env().emit_event(BankingEvent {
-                        operator: caller,
-                        status: BankTransactionStatus::EmitError(Error::LoanNotFound),
-                    });
-                    return Ok(());
+            self.transfer_between(caller, to, amount, auto_open)
+        }
+
+        /// Batch transfer (e.g. payroll) from the caller's ledger.  The caller is debited
+        /// once for the total and every recipient is credited.  Either all transfers
+        /// succeed or none do.  Returns the resulting balance of every recipient.
+        #[ink(message)]
+        pub fn batch_transfer(&mut self,
+            transfers: Vec<(AccountId, u128)>,
+            auto_open: bool) -> Result<Vec<TransferReceipt>, Error> {
+
+            let caller = self.env().caller();
+
+            // Check if the bank is open
+            if self.status != 0 {
+                return Err(self.fail(Error::BankIsClose));
+            }
+
+            if transfers.is_empty() {
+                return Err(self.fail(Error::InvalidParameter));
+            }
+            if transfers.len() > usize::from(self.max_batch_size) {
+                return Err(self.fail(Error::BatchTooLarge));
+            }
+
+            // Validate every recipient, then debit the caller once for the total
+            let mut total: u128 = 0;
+            let mut opened: Vec<AccountId> = Vec::new();
+            for (to, amount) in transfers.iter() {
+                if *to == caller || *amount == 0 {
+                    return Err(self.fail(Error::InvalidParameter));
                 }
-            };          
-
-            // If the amount is greater than or equal to the balance then we delete the loan (fully paid)
-            if amount >= self.loans[loan_index].balance {
-                // Remove the loan
-                self.loans.remove(loan_index);
-
-                // Find the ledger and add back the collateral to the account balance
-                let l = self.loans[loan_index].clone();
-                match self.ledgers.iter_mut().find(|l| l.account == account) {
-                    Some(ledger) => {
-                        ledger.balance = ledger.balance
-                            .checked_add(l.collateral)
-                            .ok_or(Error::LoanComputationOverflow)?;
-                    },
-                    None => {
-                        self.env().emit_event(BankingEvent {
-                            operator: caller,
-                            status: BankTransactionStatus::EmitError(Error::AccountNotFound),
-                        });
-                        return Ok(());
-                    }
+                self.ensure_can_receive(*to, auto_open)?;
+                if !self.ledgers.contains((self.ledger_epoch, *to)) && !opened.contains(to) {
+                    opened.push(*to);
                 }
+                total = total
+                    .checked_add(*amount)
+                    .ok_or_else(|| self.fail(Error::AccountBalanceOverflow))?;
+            }
+            if self.ledger_count.saturating_add(opened.len() as u32) > self.maximum_accounts.into() {
+                return Err(self.fail(Error::BankAccountMaxOut));
+            }
+            self.debit_ledger(caller, total)?;
 
-                self.env().emit_event(BankingEvent {
-                    operator: caller,
-                    status: BankTransactionStatus::EmitSuccess(Success::LoanFullyPaidSuccess),
+            // Credit every recipient
+            let mut receipts = Vec::with_capacity(transfers.len());
+            for (to, amount) in transfers.into_iter() {
+                let ledger = self.credit_ledger(to, amount, auto_open)?;
+
+                self.env().emit_event(Transfer {
+                    from: caller,
+                    to,
+                    amount,
+                });
+
+                receipts.push(TransferReceipt {
+                    to,
+                    amount,
+                    balance: ledger.balance,
                 });
-                return Ok(());
             }
 
-            // Update the paid amount and balance
-            let loan = &mut self.loans[loan_index];
+            Ok(receipts)
+        }
 
-            loan.paid_amount = loan.paid_amount
-                .checked_add(amount)
-                .ok_or(Error::LoanComputationOverflow)?;
+        /// Allow a spender to transfer up to `amount` from the caller's ledger.  Replaces
+        /// any existing allowance.
+        #[ink(message)]
+        pub fn approve(&mut self,
+            spender: AccountId,
+            amount: u128) -> Result<(), Error> {
 
-            // Recompute balance
-            loan.balance = loan.loan_amount
-                .checked_sub(loan.paid_amount)
-                .ok_or(Error::LoanComputationOverflow)?;
-            
+            let caller = self.env().caller();
 
-            self.env().emit_event(BankingEvent {
-                operator: caller,
-                status: BankTransactionStatus::EmitSuccess(Success::LoanPaymentSuccess),
-            });
+            if spender == caller {
+                return Err(self.fail(Error::InvalidParameter));
+            }
+
+            self.set_allowance(caller, spender, amount);
 
             Ok(())
         }
-        
-        /// Liquidate loan
+
+        /// Increase the allowance of a spender on the caller's ledger
         #[ink(message)]
-        pub fn loan_liquidation(&mut self,
-            price: u128) -> Result<(), Error> {
+        pub fn increase_allowance(&mut self,
+            spender: AccountId,
+            delta: u128) -> Result<(), Error> {
+
+            let caller = self.env().caller();
+
+            if spender == caller {
+                return Err(self.fail(Error::InvalidParameter));
+            }
+
+            let amount = self.allowance(caller, spender)
+                .checked_add(delta)
+                .ok_or_else(|| self.fail(Error::AccountBalanceOverflow))?;
+            self.set_allowance(caller, spender, amount);
+
+            Ok(())
+        }
+
+        /// Decrease the allowance of a spender on the caller's ledger
+        #[ink(message)]
+        pub fn decrease_allowance(&mut self,
+            spender: AccountId,
+            delta: u128) -> Result<(), Error> {
+
+            let caller = self.env().caller();
+
+            let amount = self.allowance(caller, spender)
+                .checked_sub(delta)
+                .ok_or_else(|| self.fail(Error::AllowanceInsufficient))?;
+            self.set_allowance(caller, spender, amount);
+
+            Ok(())
+        }
+
+        /// Get the amount a spender can still transfer from an owner's ledger
+        #[ink(message)]
+        pub fn allowance(&self,
+            owner: AccountId,
+            spender: AccountId) -> u128 {
+            self.allowances.get((self.allowance_epoch, owner, spender)).unwrap_or(0)
+        }
+
+        /// Transfer from an owner's ledger to another ledger in the bank, spending the
+        /// allowance given to the caller.  The recipient account must exist.
+        #[ink(message)]
+        pub fn transfer_from(&mut self,
+            owner: AccountId,
+            to: AccountId,
+            amount: u128) -> Result<(), Error> {
 
-            // Loan payment can only be called by the manager based on the price oracle
             let caller = self.env().caller();
-            if self.env().caller() != self.manager {
-                self.env().emit_event(BankingEvent {
-                    operator: caller,
-                    status: BankTransactionStatus::EmitError(Error::BadOrigin),
-                });
-                return Ok(());
-            } 
 
             // Check if the bank is open
             if self.status != 0 {
-                self.env().emit_event(BankingEvent {
-                    operator: caller,
-                    status: BankTransactionStatus::EmitError(Error::BankIsClose),
-                });
-                return Ok(());
-            } 
+                return Err(self.fail(Error::BankIsClose));
+            }
 
-            // Loop through the loans and check if the liquidity price is higher than the price
-            // liquidate the loan by removing it.
-            let liquidation_indices: Vec<usize> = self.loans
-                .iter()
-                .enumerate()
-                .filter(|(_, l)| l.liquidation_price >= price)
-                .map(|(i, _)| i)
-                .collect();
+            let allowance = self.allowance(owner, caller);
+            if allowance < amount {
+                return Err(self.fail(Error::AllowanceInsufficient));
+            }
+
+            self.transfer_between(owner, to, amount, false)?;
+            self.set_allowance(owner, caller, allowance - amount);
+
+            Ok(())
+        }
+
+        /// Register a standing order paying `amount` from the caller's ledger to the
+        /// recipient every `interval_days` days (daily blocks) until `end_block`.  The
+        /// first payment is due one interval after creation.
+        #[ink(message)]
+        pub fn create_standing_order(&mut self,
+            recipient: AccountId,
+            amount: u128,
+            interval_days: u16,
+            end_block: BlockNumber) -> Result<u32, Error> {
+
+            let caller = self.env().caller();
+            let current_block = self.env().block_number();
+
+            let ledger = self.ledgers.get((self.ledger_epoch, caller))
+                .ok_or_else(|| self.fail(Error::AccountNotFound))?;
+            self.ensure_active(&ledger)?;
 
-            // Remove in reverse order to preserve indices during removal
-            for i in liquidation_indices.iter().rev() {
-                self.loans.remove(*i);
+            if recipient == caller || amount == 0 || interval_days == 0 || end_block <= current_block {
+                return Err(self.fail(Error::InvalidParameter));
             }
 
-            self.env().emit_event(BankingEvent {
-                operator: caller,
-                status: BankTransactionStatus::EmitSuccess(Success::LoanLiquidationSuccess),
+            let mut order_ids = self.order_ids_of(caller);
+            if order_ids.len() >= MAXIMUM_STANDING_ORDERS {
+                return Err(self.fail(Error::TooManyStandingOrders));
+            }
+
+            let next_execution_block = current_block
+                .checked_add(self.order_interval(interval_days)?)
+                .ok_or_else(|| self.fail(Error::InvalidParameter))?;
+
+            let id = self.next_order_id;
+            self.standing_orders.insert(id, &StandingOrder {
+                id,
+                owner: caller,
+                recipient,
+                amount,
+                interval_days,
+                next_execution_block,
+                end_block,
+                failures: 0,
             });
+            order_ids.push(id);
+            self.orders_by_owner.insert(caller, &order_ids);
+            self.next_order_id = id.wrapping_add(1);
 
-            Ok(())
+            self.env().emit_event(StandingOrderCreated {
+                id,
+                owner: caller,
+                recipient,
+                amount,
+                interval_days,
+                end_block,
+            });
+
+            Ok(id)
         }
 
-        /// Get balance of an account
+        /// Cancel a standing order (order owner only)
         #[ink(message)]
-        pub fn get_balance(&self,
-            account: AccountId) ->  Option<Ledger> {
+        pub fn cancel_standing_order(&mut self,
+            id: u32) -> Result<(), Error> {
 
-            for ledger in self.ledgers.iter() {
-                if ledger.account == account {
-                    return Some(ledger.clone()); 
-                }
+            let order = self.standing_order(id)
+                .ok_or_else(|| self.fail(Error::StandingOrderNotFound))?;
+
+            if self.env().caller() != order.owner {
+                return Err(self.fail(Error::BadOrigin));
             }
 
-            None
+            self.remove_standing_order(&order);
+
+            self.env().emit_event(StandingOrderCancelled { id });
+
+            Ok(())
         }
 
-    }
+        /// Execute the due standing orders with ids from `cursor`, at most `limit` orders
+        /// (capped at the maximum batch size).  Anyone (e.g. a keeper) can execute them.
+        /// An order that cannot be paid is skipped and its failure recorded.  Returns the
+        /// cursor to resume from, the next order id once every order was visited.
+        #[ink(message)]
+        pub fn execute_due_orders(&mut self,
+            cursor: u32,
+            limit: u32) -> Result<u32, Error> {
 
-    /// Unit tests
-    #[cfg(test)]
-    mod tests {
-        /// Imports all the definitions from the outer scope so we can use them here.
-        use super::*;
+            // Check if the bank is open
+            if self.status != 0 {
+                return Err(self.fail(Error::BankIsClose));
+            }
 
-        /// We test if the default constructor does its job.
-        #[ink::test]
-        fn default_works() {
-            let Bank = Bank::default();
+            if limit == 0 {
+                return Err(self.fail(Error::InvalidParameter));
+            }
+
+            let current_block = self.env().block_number();
+            let limit = limit.min(self.max_batch_size.into());
+            let end = cursor.saturating_add(limit).min(self.next_order_id);
+
+            for id in cursor.max(self.first_order_id)..end {
+                let Some(mut order) = self.standing_order(id) else {
+                    continue;
+                };
+                if order.next_execution_block > current_block {
+                    continue;
+                }
+                if order.next_execution_block > order.end_block {
+                    self.remove_standing_order(&order);
+                    continue;
+                }
+
+                // Both ledgers are settled first, so the transfer fails before any change
+                let paid = self.can_transfer(order.owner, order.recipient, order.amount)
+                    && self.settle_ledger(order.owner).is_ok()
+                    && self.settle_ledger(order.recipient).is_ok()
+                    && self.transfer_between(order.owner, order.recipient, order.amount, false).is_ok();
+                if paid {
+                    self.env().emit_event(StandingOrderExecuted {
+                        id,
+                        amount: order.amount,
+                    });
+                } else {
+                    order.failures = order.failures.saturating_add(1);
+                    self.env().emit_event(StandingOrderFailed {
+                        id,
+                        failures: order.failures,
+                    });
+                }
+
+                order.next_execution_block = order.next_execution_block
+                    .saturating_add(self.order_interval(order.interval_days)?);
+                if order.next_execution_block > order.end_block {
+                    self.remove_standing_order(&order);
+                } else {
+                    self.standing_orders.insert(id, &order);
+                }
+            }
+
+            Ok(end)
+        }
+
+        /// Get the standing orders of an account
+        #[ink(message)]
+        pub fn get_standing_orders(&self,
+            owner: AccountId) -> Vec<StandingOrder> {
+            self.order_ids_of(owner)
+                .into_iter()
+                .filter_map(|id| self.standing_order(id))
+                .collect()
+        }
+
+        /// Create an escrow.  The amount is moved from the caller's free balance to its
+        /// locked balance until the escrow is released to the payee or refunded.
+        #[ink(message)]
+        pub fn create_escrow(&mut self,
+            payee: AccountId,
+            arbiter: AccountId,
+            amount: u128,
+            expiry_block: BlockNumber) -> Result<u32, Error> {
+
+            let caller = self.env().caller();
+
+            // Check if the bank is open
+            if self.status != 0 {
+                return Err(self.fail(Error::BankIsClose));
+            }
+
+            if payee == caller || arbiter == caller || arbiter == payee
+                || amount == 0 || expiry_block <= self.env().block_number() {
+                return Err(self.fail(Error::InvalidParameter));
+            }
+            if !self.ledgers.contains((self.ledger_epoch, payee)) {
+                return Err(self.fail(Error::AccountNotFound));
+            }
+
+            // Only the payer's escrows are indexed, so nobody can grow the escrow list of
+            // another account
+            let id = self.next_escrow_id;
+            let mut escrow_ids = self.escrow_ids_of(caller);
+            if escrow_ids.len() >= MAXIMUM_ESCROWS {
+                return Err(self.fail(Error::TooManyEscrows));
+            }
+            escrow_ids.push(id);
+            self.escrows_by_payer.insert(caller, &escrow_ids);
+
+            // Lock the amount on the payer's ledger
+            let mut ledger = self.debit_ledger(caller, amount)?;
+            let locked = ledger.locked
+                .checked_add(amount)
+                .ok_or_else(|| self.fail(Error::AccountBalanceOverflow))?;
+            self.set_locked(&mut ledger, locked)?;
+            self.ledgers.insert((self.ledger_epoch, caller), &ledger);
+
+            self.escrows.insert(id, &Escrow {
+                id,
+                payer: caller,
+                payee,
+                arbiter,
+                amount,
+                expiry_block,
+            });
+            self.next_escrow_id = id.wrapping_add(1);
+
+            self.env().emit_event(EscrowCreated {
+                id,
+                payer: caller,
+                payee,
+                arbiter,
+                amount,
+                expiry_block,
+            });
+
+            Ok(id)
+        }
+
+        /// Release an escrow to the payee (payer or arbiter only)
+        #[ink(message)]
+        pub fn release_escrow(&mut self,
+            id: u32) -> Result<(), Error> {
+
+            let caller = self.env().caller();
+
+            // Check if the bank is open
+            if self.status != 0 {
+                return Err(self.fail(Error::BankIsClose));
+            }
+
+            let escrow = self.escrow(id)
+                .ok_or_else(|| self.fail(Error::EscrowNotFound))?;
+            if caller != escrow.payer && caller != escrow.arbiter {
+                return Err(self.fail(Error::BadOrigin));
+            }
+
+            self.unlock_escrow(&escrow)?;
+            self.credit_ledger(escrow.payee, escrow.amount, false)?;
+
+            self.env().emit_event(EscrowReleased {
+                id,
+                operator: caller,
+            });
+
+            Ok(())
+        }
+
+        /// Refund an escrow to the payer (payee or arbiter only, anyone once expired)
+        #[ink(message)]
+        pub fn refund_escrow(&mut self,
+            id: u32) -> Result<(), Error> {
+
+            let caller = self.env().caller();
+
+            // Check if the bank is open
+            if self.status != 0 {
+                return Err(self.fail(Error::BankIsClose));
+            }
+
+            let escrow = self.escrow(id)
+                .ok_or_else(|| self.fail(Error::EscrowNotFound))?;
+            if caller != escrow.payee && caller != escrow.arbiter
+                && self.env().block_number() <= escrow.expiry_block {
+                return Err(self.fail(Error::BadOrigin));
+            }
+
+            self.unlock_escrow(&escrow)?;
+            self.credit_ledger(escrow.payer, escrow.amount, false)?;
+
+            self.env().emit_event(EscrowRefunded {
+                id,
+                operator: caller,
+            });
+
+            Ok(())
+        }
+
+        /// Get the open escrows paid by an account.  Payees and arbiters find their
+        /// escrows through the `EscrowCreated` events.
+        #[ink(message)]
+        pub fn get_escrows(&self,
+            payer: AccountId) -> Vec<Escrow> {
+            self.escrow_ids_of(payer)
+                .into_iter()
+                .filter_map(|id| self.escrow(id))
+                .collect()
+        }
+
+        /// Get an open escrow
+        #[ink(message)]
+        pub fn get_escrow(&self,
+            id: u32) -> Option<Escrow> {
+            self.escrow(id)
+        }
+
+        /// Set the interest rate tiers of a product (interest officer only).  Product 0 is
+        /// the default product of every account.  Rates are annual, in basis points, and
+        /// apply to the whole balance of the first tier whose `up_to` is not below it;
+        /// balances above the last tier earn nothing.  The interest earned under the
+        /// previous tiers is kept.
+        #[ink(message)]
+        pub fn set_rate_tiers(&mut self,
+            product: u8,
+            tiers: Vec<RateTier>) -> Result<(), Error> {
+
+            self.ensure_role(Role::InterestOfficer)?;
+
+            if tiers.len() > MAXIMUM_RATE_TIERS
+                || tiers.windows(2).any(|pair| pair[0].up_to >= pair[1].up_to)
+                || tiers.iter().any(|tier| u128::from(tier.rate_bps) > BASIS_POINTS) {
+                return Err(self.fail(Error::InvalidParameter));
+            }
+
+            // The new tiers take effect now, the current tiers stop earning
+            let start_days = self.current_interest_days()?;
+            let version = self.rate_versions.get(product).unwrap_or(0)
+                .checked_add(1)
+                .ok_or_else(|| self.fail(Error::InvalidParameter))?;
+            self.rate_schedules.insert((product, version), &RateSchedule {
+                tiers: tiers.clone(),
+                start_days,
+            });
+            self.rate_versions.insert(product, &version);
+
+            self.env().emit_event(RateTiersSet {
+                product,
+                version,
+                tiers,
+            });
+
+            Ok(())
+        }
+
+        /// Set the product of an account (teller role).  The interest earned so far is
+        /// settled first.  The product must have rate tiers, except the default product 0.
+        #[ink(message)]
+        pub fn set_account_product(&mut self,
+            account: AccountId,
+            product: u8) -> Result<(), Error> {
+
+            self.ensure_role(Role::Teller)?;
+
+            if product != 0 && !self.rate_versions.contains(product) {
+                return Err(self.fail(Error::InvalidParameter));
+            }
+
+            let mut ledger = self.load_ledger(account)?;
+            self.ensure_active(&ledger)?;
+            ledger.product = product;
+            ledger.rate_version = self.rate_versions.get(product).unwrap_or(0);
+            self.snapshot_interest_index(&mut ledger, self.current_interest_days()?)?;
+            self.ledgers.insert((self.ledger_epoch, account), &ledger);
+
+            self.env().emit_event(AccountProductChanged {
+                account,
+                product,
+            });
+
+            Ok(())
+        }
+
+        /// Get the current rate tiers of a product and their version
+        #[ink(message)]
+        pub fn get_rate_tiers(&self,
+            product: u8) -> (u32, Vec<RateTier>) {
+            let version = self.rate_versions.get(product).unwrap_or(0);
+            (version, self.rate_schedule(product, version).tiers)
+        }
+
+        /// Preview the interest of an account: the interest earned since it was last
+        /// settled plus the interest it would earn over the next `blocks` blocks at its
+        /// current rate and the current daily blocks, up to the part of the interest pool
+        /// not owed to other accounts.  The interest the pool cannot pay is deferred.
+        #[ink(message)]
+        pub fn preview_interest(&self,
+            account: AccountId,
+            blocks: BlockNumber) -> Option<u128> {
+
+            let settled = self.ledgers.get((self.ledger_epoch, account))?;
+            let ledger = self.ledger_with_interest(account)?;
+            let pending = ledger.balance.checked_sub(settled.balance)?;
+
+            // Part of the pool left once the pending interest is settled
+            let owed = self.interest_owed
+                .checked_sub(settled.deferred_interest)?
+                .checked_add(ledger.deferred_interest)?;
+            let available = self.interest_pool
+                .checked_sub(pending)?
+                .saturating_sub(owed);
+
+            if ledger.status != AccountStatus::Active {
+                return Some(pending);
+            }
+
+            let tiers = self.rate_schedule(ledger.product, ledger.rate_version).tiers;
+            let rate_bps = tiers.get(usize::from(ledger.rate_tier))
+                .map(|tier| u128::from(tier.rate_bps))
+                .unwrap_or(0);
+
+            Self::mul_div(ledger.balance,
+                rate_bps.checked_mul(blocks.into())?,
+                BASIS_POINTS * DAYS_PER_YEAR * u128::from(self.daily_blocks))?
+                .min(available)
+                .checked_add(pending)
+        }
+
+        /// Fund the interest pool.  The approved asset is pulled from the caller into the
+        /// bank contract (see `self_deposit`).
+        #[ink(message)]
+        pub fn fund_interest_pool(&mut self,
+            amount: u128) -> Result<(), ContractError> {
+
+            let caller = self.env().caller();
+
+            if amount == 0 {
+                return Err(self.fail(Error::InvalidParameter).into());
+            }
+
+            let interest_pool = self.interest_pool
+                .checked_add(amount)
+                .ok_or_else(|| self.fail(Error::AccountBalanceOverflow))?;
+
+            // Pull the approved asset from the funder into the bank contract
+            self.env()
+                .call_runtime(&RuntimeCall::Assets(AssetsCall::TransferApproved {
+                    id: self.asset_id,
+                    owner: caller.into(),
+                    destination: self.env().account_id().into(),
+                    amount,
+                }))
+                .map_err(|_| RuntimeError::CallRuntimeFailed)?;
+
+            self.interest_pool = interest_pool;
+
+            self.env().emit_event(InterestPoolFunded {
+                funder: caller,
+                amount,
+            });
+
+            Ok(())
+        }
+
+        /// Distribute the interest pool (interest officer only) across the active ledgers
+        /// in proportion to the balance they held over the last closed statement period:
+        ///     share = amount * balance_blocks / total_balance_blocks
+        /// A distribution visits at most `limit` ledgers per call (capped at the maximum
+        /// batch size): the first call sets aside the pool not owed as deferred interest,
+        /// the ledgers are first summed then paid, and the rounding dust goes back to the
+        /// pool at the end.  Returns
+        /// whether the distribution is finished.
+        #[ink(message)]
+        pub fn distribute_interest_pool(&mut self,
+            limit: u32) -> Result<bool, Error> {
+
+            self.ensure_role(Role::InterestOfficer)?;
+
+            // Check if the bank is open
+            if self.status != 0 {
+                return Err(self.fail(Error::BankIsClose));
+            }
+
+            if limit == 0 {
+                return Err(self.fail(Error::InvalidParameter));
+            }
+
+            let mut distribution = match self.interest_distribution.take() {
+                Some(distribution) => distribution,
+                None => {
+                    let period = self.current_period.checked_sub(1)
+                        .ok_or_else(|| self.fail(Error::InvalidParameter))?;
+                    // The interest owed to the ledgers stays in the pool
+                    let amount = self.interest_pool.saturating_sub(self.interest_owed);
+                    if amount == 0 {
+                        return Err(self.fail(Error::InvalidParameter));
+                    }
+                    self.interest_pool -= amount;
+                    let id = self.next_distribution_id;
+                    self.next_distribution_id = id.wrapping_add(1);
+                    InterestDistribution {
+                        id,
+                        period,
+                        amount,
+                        ..Default::default()
+                    }
+                },
+            };
+
+            let limit = limit.min(self.max_batch_size.into());
+            let end = distribution.cursor.saturating_add(limit).min(self.ledger_count);
+
+            for index in distribution.cursor..end {
+                let Some(account) = self.ledger_accounts.get(index) else {
+                    continue;
+                };
+                let Some(ledger) = self.ledgers.get((self.ledger_epoch, account)) else {
+                    continue;
+                };
+
+                if !distribution.paying {
+                    if ledger.status != AccountStatus::Active {
+                        continue;
+                    }
+                    let balance_blocks = self.balance_blocks_in(&ledger, distribution.period)
+                        .ok_or_else(|| self.fail(Error::AccountBalanceOverflow))?;
+                    distribution.total_balance_blocks = distribution.total_balance_blocks
+                        .checked_add(balance_blocks)
+                        .ok_or_else(|| self.fail(Error::AccountBalanceOverflow))?;
+                    self.distribution_balances.insert((distribution.id, account), &balance_blocks);
+                    continue;
+                }
+
+                // Only the ledgers counted are paid, and only while they are active: an
+                // account reactivated since is not paid and the share of an account
+                // frozen since stays in the dust
+                let Some(balance_blocks) = self.distribution_balances
+                    .take((distribution.id, account)) else {
+                    continue;
+                };
+                if ledger.status != AccountStatus::Active {
+                    continue;
+                }
+
+                let share = Self::mul_div(distribution.amount,
+                    balance_blocks,
+                    distribution.total_balance_blocks)
+                    .unwrap_or(0)
+                    .min(distribution.amount - distribution.distributed);
+                if share == 0 {
+                    continue;
+                }
+
+                let mut ledger = self.load_ledger(account)?;
+                let balance = ledger.balance
+                    .checked_add(share)
+                    .ok_or_else(|| self.fail(Error::AccountBalanceOverflow))?;
+                self.set_balance(&mut ledger, balance)?;
+                ledger.period_interest = ledger.period_interest
+                    .checked_add(share)
+                    .ok_or_else(|| self.fail(Error::AccountBalanceOverflow))?;
+                self.ledgers.insert((self.ledger_epoch, account), &ledger);
+
+                distribution.distributed += share;
+                distribution.accounts += 1;
+            }
+            distribution.cursor = end;
+
+            // Pay the shares once every ledger is summed
+            if distribution.cursor >= self.ledger_count && !distribution.paying
+                && distribution.total_balance_blocks > 0 {
+                distribution.paying = true;
+                distribution.cursor = 0;
+            }
+
+            if distribution.cursor < self.ledger_count {
+                self.interest_distribution = Some(distribution);
+                return Ok(false);
+            }
+
+            // Carry the rounding dust forward
+            let dust = distribution.amount - distribution.distributed;
+            self.interest_pool = self.interest_pool
+                .checked_add(dust)
+                .ok_or_else(|| self.fail(Error::AccountBalanceOverflow))?;
+            self.interest_pool_dust = dust;
+
+            self.env().emit_event(InterestPoolDistributed {
+                period: distribution.period,
+                distributed: distribution.distributed,
+                accounts: distribution.accounts,
+                dust,
+            });
+
+            Ok(true)
+        }
+
+        /// Abort the interest pool distribution in progress (owner only).  The amount not
+        /// paid yet goes back to the pool, the shares already paid are kept.
+        #[ink(message)]
+        pub fn abort_interest_distribution(&mut self) -> Result<(), Error> {
+
+            self.ensure_owner()?;
+
+            let distribution = self.interest_distribution.take()
+                .ok_or_else(|| self.fail(Error::InvalidParameter))?;
+
+            let returned = distribution.amount - distribution.distributed;
+            self.interest_pool = self.interest_pool
+                .checked_add(returned)
+                .ok_or_else(|| self.fail(Error::AccountBalanceOverflow))?;
+
+            self.env().emit_event(InterestDistributionAborted {
+                period: distribution.period,
+                distributed: distribution.distributed,
+                returned,
+            });
+
+            Ok(())
+        }
+
+        /// Get the interest pool distribution in progress
+        #[ink(message)]
+        pub fn get_interest_distribution(&self) -> Option<InterestDistribution> {
+            self.interest_distribution.clone()
+        }
+
+        /// Get the interest pool, the rounding dust carried from the last distribution and
+        /// the interest owed to the ledgers
+        #[ink(message)]
+        pub fn get_interest_pool(&self) -> (u128, u128, u128) {
+            (self.interest_pool, self.interest_pool_dust, self.interest_owed)
+        }
+
+        /// Apply for a loan
+        /// For example: loan_amount (encoded) = $100 USDT
+        ///              price (oracle) = $0.01
+        ///              collateral (encoded) = 11,000 
+        ///              threshold (setup) = 5% (Upon liquidation the value must be $105)
+        ///              liquidation_price (computed) = $105 / 11,000 = $0.00954545454
+        /// Rules:
+        ///     1. The account must have a balance greater than the collateral.
+        ///     2. The collateral must be within the threshold.
+        ///     3. To have an acceptable liquidation_price the collateral must take into consideration the 
+        ///        volatility of the asset price or else the loan will immediately liquidated.
+        ///     4. The loan amount is disbursed in the loan asset, if the transfer fails the application
+        ///        is reverted.
+        /// Returns the proposal id if the application needs the approval of the approvers.
+        #[ink(message)]
+        pub fn loan_application(&mut self,
+            account: AccountId,
+            loan_amount: u128,
+            price: u128,
+            collateral: u128) -> Result<Option<u32>, ContractError> {
+
+            // Loan application can only be called by a loan officer due to oracle input
+            self.ensure_role(Role::LoanOfficer)?;
+
+            // High-value actions need the approval of the approvers
+            if self.requires_approval(loan_amount) {
+                if let Some(id) = self.create_proposal(ProposalAction::LoanApplication { account, loan_amount, price, collateral })? {
+                    return Ok(Some(id));
+                }
+            }
+
+            self.execute_loan_application(account, loan_amount, price, collateral)?;
+
+            Ok(None)
+        }
+
+        /// Pay loan.  Manager path after accepting the loan asset transfer off-chain,
+        /// borrowers use `repay_loan`.  The amount can not exceed the loan balance.
+        #[ink(message)]
+        pub fn loan_payment(&mut self,
+            account: AccountId,
+            amount: u128) -> Result<(), ContractError> {
+
+            // Loan payment can only be called by a loan officer after accepting USDT transfer
+            let caller = self.env().caller();
+            self.ensure_role(Role::LoanOfficer)?;
+
+            // Check if the bank is open
+            if self.status != 0 {
+                return Err(self.fail(Error::BankIsClose).into());
+            } 
+
+            // Search for the loan
+            let loan = self.loans.get((self.ledger_epoch, account))
+                .ok_or_else(|| self.fail(Error::LoanNotFound))?;
+
+            // The payment was accepted off-chain, so it can not exceed the outstanding balance
+            if amount == 0 || amount > loan.balance {
+                return Err(self.fail(Error::InvalidParameter).into());
+            }
+
+            let fully_paid = self.apply_loan_payment(account, amount)?;
+
+            let success = if fully_paid {
+                Success::LoanFullyPaidSuccess
+            } else {
+                Success::LoanPaymentSuccess
+            };
+            self.env().emit_event(BankingEvent {
+                operator: caller,
+                status: BankTransactionStatus::EmitSuccess(success),
+            });
+
+            Ok(())
+        }
+
+        /// Repay loan.  The borrower must first approve the bank contract to spend
+        /// the loan asset through `Assets::approve_transfer`.  Only the outstanding
+        /// balance is pulled, so an overpayment never leaves the borrower.
+        #[ink(message)]
+        pub fn repay_loan(&mut self,
+            amount: u128) -> Result<(), ContractError> {
+
+            let caller = self.env().caller();
+
+            // Check if the bank is open
+            if self.status != 0 {
+                return Err(self.fail(Error::BankIsClose).into());
+            } 
+
+            // Search for the loan of the caller
+            let loan = self.loans.get((self.ledger_epoch, caller))
+                .ok_or_else(|| self.fail(Error::LoanNotFound))?;
+
+            // Only an active borrower can repay on-chain
+            let ledger = self.ledgers.get((self.ledger_epoch, caller))
+                .ok_or_else(|| self.fail(Error::AccountNotFound))?;
+            self.ensure_active(&ledger)?;
+
+            let payment = amount.min(loan.balance);
+
+            // Pull the approved loan asset from the borrower into the bank contract
+            self.env()
+                .call_runtime(&RuntimeCall::Assets(AssetsCall::TransferApproved {
+                    id: self.loan_asset_id,
+                    owner: caller.into(),
+                    destination: self.env().account_id().into(),
+                    amount: payment,
+                }))
+                .map_err(|_| RuntimeError::CallRuntimeFailed)?;
+
+            let fully_paid = self.apply_loan_payment(caller, payment)?;
+
+            let success = if fully_paid {
+                Success::LoanFullyPaidSuccess
+            } else {
+                Success::LoanPaymentSuccess
+            };
+            self.env().emit_event(BankingEvent {
+                operator: caller,
+                status: BankTransactionStatus::EmitSuccess(success),
+            });
+
+            Ok(())
+        }
+        
+        /// Liquidate loan
+        #[ink(message)]
+        pub fn loan_liquidation(&mut self,
+            price: u128) -> Result<(), Error> {
+
+            // Loan liquidation can only be called by an oracle based on the price oracle
+            let caller = self.env().caller();
+            self.ensure_role(Role::Oracle)?;
+
+            // Check if the bank is open
+            if self.status != 0 {
+                return Err(self.fail(Error::BankIsClose));
+            } 
+
+            // Loop through the loans and check if the liquidity price is higher than the price
+            // liquidate the loan.
+            let liquidated: Vec<Loan> = (0..self.loan_count)
+                .filter_map(|index| self.loan_accounts.get(index))
+                .filter_map(|account| self.loans.get((self.ledger_epoch, account)))
+                .filter(|l| l.liquidation_price >= price)
+                .collect();
+
+            // Process after the scan so the index is not modified while iterating
+            for loan in liquidated.iter() {
+                // Seize the locked collateral from the borrower's ledger
+                if let Some(mut ledger) = self.ledgers.get((self.ledger_epoch, loan.account)) {
+                    let locked = ledger.locked
+                        .checked_sub(loan.collateral)
+                        .ok_or_else(|| self.fail(Error::LoanComputationOverflow))?;
+                    self.set_locked(&mut ledger, locked)?;
+                    self.ledgers.insert((self.ledger_epoch, loan.account), &ledger);
+                }
+
+                // Move the collateral to the liquidation treasury
+                self.treasury = self.treasury
+                    .checked_add(loan.collateral)
+                    .ok_or_else(|| self.fail(Error::LoanComputationOverflow))?;
+
+                // Record the shortfall between the collateral value and the loan balance
+                let collateral_value = loan.collateral
+                    .checked_mul(price)
+                    .ok_or_else(|| self.fail(Error::LoanComputationOverflow))?;
+                let shortfall = loan.balance.saturating_sub(collateral_value);
+                self.bad_debt = self.bad_debt
+                    .checked_add(shortfall)
+                    .ok_or_else(|| self.fail(Error::LoanComputationOverflow))?;
+
+                self.loans.remove((self.ledger_epoch, loan.account));
+                self.remove_loan_index(loan.account);
+
+                self.env().emit_event(LoanLiquidated {
+                    account: loan.account,
+                    seized: loan.collateral,
+                    bad_debt: shortfall,
+                });
+            }
+
+            self.env().emit_event(BankingEvent {
+                operator: caller,
+                status: BankTransactionStatus::EmitSuccess(Success::LoanLiquidationSuccess),
+            });
+
+            Ok(())
+        }
+
+        /// Get balance of an account
+        #[ink(message)]
+        pub fn get_balance(&self,
+            account: AccountId) ->  Option<Ledger> {
+
+            self.ledger_with_interest(account)
+        }
+
+        /// Freeze an account with a reason code.  Only the manager can freeze accounts.
+        #[ink(message)]
+        pub fn freeze_account(&mut self,
+            account: AccountId,
+            reason: u8) -> Result<(), Error> {
+
+            self.ensure_manager()?;
+            self.change_account_status(account, AccountStatus::Frozen, reason)
+        }
+
+        /// Unfreeze a frozen or dormant account with a reason code.  Only the manager can
+        /// unfreeze accounts.
+        #[ink(message)]
+        pub fn unfreeze_account(&mut self,
+            account: AccountId,
+            reason: u8) -> Result<(), Error> {
+
+            self.ensure_manager()?;
+            self.change_account_status(account, AccountStatus::Active, reason)
+        }
+
+        /// Mark an active account as dormant with a reason code.  Only the manager can
+        /// mark accounts as dormant.
+        #[ink(message)]
+        pub fn mark_dormant(&mut self,
+            account: AccountId,
+            reason: u8) -> Result<(), Error> {
+
+            self.ensure_manager()?;
+            self.change_account_status(account, AccountStatus::Dormant, reason)
+        }
+
+        /// Get the time-weighted average free balance of an account between two blocks
+        /// (from inclusive, to exclusive).  `to_block` cannot be in the future.
+        #[ink(message)]
+        pub fn adb_of(&self,
+            account: AccountId,
+            from_block: BlockNumber,
+            to_block: BlockNumber) -> Option<u128> {
+
+            if from_block >= to_block || to_block > self.env().block_number() {
+                return None;
+            }
+
+            let ledger = self.ledgers.get((self.ledger_epoch, account))?;
+            let from = self.cumulative_balance_at(&ledger, from_block.into())?;
+            let to = self.cumulative_balance_at(&ledger, to_block.into())?;
+
+            to.checked_sub(from)?
+                .checked_div(u128::from(to_block - from_block))
+        }
+
+        /// Close the current statement period (keeper role).  Account statements are
+        /// snapshotted lazily, when the account is next touched.  Returns the closed
+        /// period.
+        #[ink(message)]
+        pub fn close_period(&mut self) -> Result<u32, Error> {
+
+            self.ensure_role(Role::Keeper)?;
+
+            let end_block = self.env().block_number();
+            if end_block <= self.period_start_block {
+                return Err(self.fail(Error::InvalidParameter));
+            }
+
+            let period = self.current_period;
+            let end_days = self.current_interest_days()?;
+            self.period_end_blocks.insert(period, &end_block);
+            self.period_end_days.insert(period, &end_days);
+            self.current_period = period
+                .checked_add(1)
+                .ok_or_else(|| self.fail(Error::InvalidParameter))?;
+            self.period_start_block = end_block;
+
+            self.env().emit_event(PeriodClosed {
+                period,
+                end_block,
+            });
+
+            Ok(period)
+        }
+
+        /// Roll over the statements and the rate tiers of an account, at most `limit`
+        /// periods or rate tier versions per call (capped at the maximum batch size).
+        /// Anyone can call it; an account behind by more than the maximum rollover periods
+        /// or rate tier versions must be rolled over before it is used.  Returns whether
+        /// the account is rolled over to the current period and rate tiers.
+        #[ink(message)]
+        pub fn rollover_account(&mut self,
+            account: AccountId,
+            limit: u32) -> Result<bool, Error> {
+
+            // Check if the bank is open
+            if self.status != 0 {
+                return Err(self.fail(Error::BankIsClose));
+            }
+
+            if limit == 0 {
+                return Err(self.fail(Error::InvalidParameter));
+            }
+
+            let mut ledger = self.ledgers.get((self.ledger_epoch, account))
+                .ok_or_else(|| self.fail(Error::AccountNotFound))?;
+            let mut rolled_over = false;
+            for _ in 0..limit.min(self.max_batch_size.into()) {
+                rolled_over = !self.rollover_step(&mut ledger)?;
+                if rolled_over {
+                    break;
+                }
+            }
+            self.ledgers.insert((self.ledger_epoch, account), &ledger);
+
+            Ok(rolled_over || (ledger.period == self.current_period
+                && ledger.rate_version == self.rate_versions.get(ledger.product).unwrap_or(0)))
+        }
+
+        /// Get the statement of an account for a closed period
+        #[ink(message)]
+        pub fn get_statement(&self,
+            account: AccountId,
+            period: u32) -> Option<Statement> {
+
+            if let Some(statement) = self.statements.get((account, period)) {
+                return Some(statement);
+            }
+
+            // Not snapshotted yet
+            let ledger = self.ledgers.get((self.ledger_epoch, account))?;
+            self.build_statement(&ledger, period)
+        }
+
+        /// Get the current statement period and its start block
+        #[ink(message)]
+        pub fn get_current_period(&self) -> (u32, BlockNumber) {
+            (self.current_period, self.period_start_block)
+        }
+
+        /// Get the liquidation treasury and the recorded bad debt
+        #[ink(message)]
+        pub fn get_treasury(&self) -> (u128, u128) {
+            (
+                self.treasury,
+                self.bad_debt,
+            )
+        }
+
+        /// Withdraw seized collateral from the liquidation treasury (owner only)
+        #[ink(message)]
+        pub fn withdraw_treasury(&mut self,
+            to: AccountId,
+            amount: u128) -> Result<(), ContractError> {
+
+            self.ensure_owner()?;
+
+            if amount == 0 {
+                return Err(self.fail(Error::InvalidParameter).into());
+            }
+            if amount > self.treasury {
+                return Err(self.fail(Error::AccountBalanceInsufficient).into());
+            }
+
+            self.treasury -= amount;
+
+            // Transfer the asset out of the bank contract
+            self.env()
+                .call_runtime(&RuntimeCall::Assets(AssetsCall::Transfer {
+                    id: self.asset_id,
+                    target: to.into(),
+                    amount,
+                }))
+                .map_err(|_| RuntimeError::CallRuntimeFailed)?;
+
+            self.env().emit_event(TreasuryWithdrawn {
+                to,
+                amount,
+            });
+
+            Ok(())
+        }
+
+        /// Configure the multisig.  Deposit, withdraw, credit and loan application amounts above
+        /// `approval_amount` become proposals that need `approval_threshold` approvals.
+        /// A zero threshold disables the multisig.
+        #[ink(message)]
+        pub fn set_approvers(&mut self,
+            approvers: Vec<AccountId>,
+            approval_threshold: u8,
+            approval_amount: u128,
+            proposal_lifetime: BlockNumber) -> Result<(), Error> {
+
+            let caller = self.env().caller();
+            self.ensure_owner()?;
+
+            let mut approvers = approvers;
+            approvers.sort();
+            approvers.dedup();
+
+            if approvers.len() > MAXIMUM_APPROVERS {
+                return Err(self.fail(Error::TooManyApprovers));
+            }
+            if usize::from(approval_threshold) > approvers.len() || proposal_lifetime == 0 {
+                return Err(self.fail(Error::InvalidParameter));
+            }
+
+            self.approvers.set(&approvers);
+            self.approval_threshold = approval_threshold;
+            self.approval_amount = approval_amount;
+            self.proposal_lifetime = proposal_lifetime;
+
+            self.env().emit_event(BankingEvent {
+                operator: caller,
+                status: BankTransactionStatus::EmitSuccess(Success::ApproversSetSuccess),
+            });
+
+            Ok(())
+        }
+
+        /// Approve a proposal.  The proposal is executed with the approval that reaches
+        /// the approval threshold.
+        #[ink(message)]
+        pub fn approve_proposal(&mut self,
+            id: u32) -> Result<(), ContractError> {
+
+            let caller = self.env().caller();
+            let approvers = self.approvers.get_or_default();
+            if !approvers.contains(&caller) {
+                return Err(self.fail(Error::BadOrigin).into());
+            }
+
+            let mut proposal = self.proposals.get(id)
+                .ok_or_else(|| self.fail(Error::ProposalNotFound))?;
+
+            if self.env().block_number() > proposal.expiry_block {
+                return Err(self.fail(Error::ProposalExpired).into());
+            }
+            if proposal.approvals.contains(&caller) {
+                return Err(self.fail(Error::ProposalAlreadyApproved).into());
+            }
+            proposal.approvals.push(caller);
+
+            // Only approvals of current approvers count
+            let approvals = proposal.approvals.iter()
+                .filter(|a| approvers.contains(*a))
+                .count();
+
+            self.env().emit_event(ProposalApproved {
+                id,
+                approver: caller,
+                approvals: u8::try_from(approvals).unwrap_or(u8::MAX),
+            });
+
+            if approvals < usize::from(self.approval_threshold) {
+                self.proposals.insert(id, &proposal);
+                return Ok(());
+            }
+
+            // Threshold reached, execute the action
+            self.proposals.remove(id);
+            match proposal.action {
+                ProposalAction::Deposit { account, amount } => {
+                    self.execute_deposit(account, amount)?
+                },
+                ProposalAction::Withdraw { account, amount } => {
+                    self.execute_withdraw(account, amount)?
+                },
+                ProposalAction::Credit { account, amount } => {
+                    self.execute_credit(account, amount)?
+                },
+                ProposalAction::LoanApplication { account, loan_amount, price, collateral } => {
+                    self.execute_loan_application(account, loan_amount, price, collateral)?
+                },
+            }
+
+            self.env().emit_event(ProposalExecuted {
+                id,
+            });
+
+            Ok(())
+        }
+
+        /// Cancel a proposal.  Can be done by the proposer or the owner, or by anyone
+        /// once the proposal has expired.
+        #[ink(message)]
+        pub fn cancel_proposal(&mut self,
+            id: u32) -> Result<(), Error> {
+
+            let caller = self.env().caller();
+            let proposal = self.proposals.get(id)
+                .ok_or_else(|| self.fail(Error::ProposalNotFound))?;
+
+            let expired = self.env().block_number() > proposal.expiry_block;
+            if caller != proposal.proposer && caller != self.owner && !expired {
+                return Err(self.fail(Error::BadOrigin));
+            }
+
+            self.proposals.remove(id);
+
+            self.env().emit_event(ProposalCancelled {
+                id,
+            });
+
+            Ok(())
+        }
+
+        /// Get a proposal
+        #[ink(message)]
+        pub fn get_proposal(&self,
+            id: u32) -> Option<Proposal> {
+
+            self.proposals.get(id)
+        }
+
+        /// Get the multisig configuration: approvers, approval threshold, approval amount
+        /// and proposal lifetime.
+        #[ink(message)]
+        pub fn get_approvers(&self) -> (Vec<AccountId>, u8, u128, BlockNumber) {
+            (
+                self.approvers.get_or_default(),
+                self.approval_threshold,
+                self.approval_amount,
+                self.proposal_lifetime,
+            )
+        }
+
+        /// Start an ownership transfer.  The new owner must accept it.
+        #[ink(message)]
+        pub fn transfer_ownership(&mut self,
+            new_owner: AccountId) -> Result<(), Error> {
+
+            // Only the owner can transfer the ownership
+            self.ensure_owner()?;
+
+            self.pending_owner = Some(new_owner);
+
+            self.env().emit_event(OwnershipTransferStarted {
+                previous_owner: self.owner,
+                new_owner,
+            });
+
+            Ok(())
+        }
+
+        /// Accept a pending ownership transfer.  Called by the new owner.
+        #[ink(message)]
+        pub fn accept_ownership(&mut self) -> Result<(), Error> {
+
+            let caller = self.env().caller();
+            let pending_owner = self.pending_owner
+                .ok_or_else(|| self.fail(Error::PendingTransferNotFound))?;
+            if caller != pending_owner {
+                return Err(self.fail(Error::BadOrigin));
+            }
+
+            let previous_owner = self.owner;
+            self.owner = pending_owner;
+            self.pending_owner = None;
+
+            self.env().emit_event(OwnershipTransferred {
+                previous_owner,
+                new_owner: pending_owner,
+            });
+
+            Ok(())
+        }
+
+        /// Cancel a pending ownership transfer
+        #[ink(message)]
+        pub fn cancel_ownership_transfer(&mut self) -> Result<(), Error> {
+
+            // Only the owner can cancel the transfer
+            self.ensure_owner()?;
+
+            let cancelled_owner = self.pending_owner
+                .take()
+                .ok_or_else(|| self.fail(Error::PendingTransferNotFound))?;
+
+            self.env().emit_event(OwnershipTransferCancelled {
+                owner: self.owner,
+                cancelled_owner,
+            });
+
+            Ok(())
+        }
+
+        /// Accept a pending manager proposal.  Called by the new manager.  Managers are
+        /// proposed through a time-locked `TimelockedChange::Manager` change.
+        #[ink(message)]
+        pub fn accept_manager(&mut self) -> Result<(), Error> {
+
+            let caller = self.env().caller();
+            let pending_manager = self.pending_manager
+                .ok_or_else(|| self.fail(Error::PendingTransferNotFound))?;
+            if caller != pending_manager {
+                return Err(self.fail(Error::BadOrigin));
+            }
+
+            let previous_manager = self.manager;
+            self.manager = pending_manager;
+            self.pending_manager = None;
+
+            self.env().emit_event(ManagerChanged {
+                previous_manager,
+                new_manager: pending_manager,
+            });
+
+            Ok(())
+        }
+
+        /// Cancel a pending manager proposal
+        #[ink(message)]
+        pub fn cancel_manager_proposal(&mut self) -> Result<(), Error> {
+
+            // Only the owner can cancel the proposal
+            self.ensure_owner()?;
+
+            let cancelled_manager = self.pending_manager
+                .take()
+                .ok_or_else(|| self.fail(Error::PendingTransferNotFound))?;
+
+            self.env().emit_event(ManagerProposalCancelled {
+                manager: self.manager,
+                cancelled_manager,
+            });
+
+            Ok(())
+        }
+
+        /// Get the pending owner and pending manager
+        #[ink(message)]
+        pub fn get_pending_transfers(&self) -> (Option<AccountId>, Option<AccountId>) {
+            (
+                self.pending_owner,
+                self.pending_manager,
+            )
+        }
+
+        /// Grant a role to an account.  Only the owner can grant roles.
+        #[ink(message)]
+        pub fn grant_role(&mut self,
+            role: Role,
+            account: AccountId) -> Result<(), Error> {
+
+            let caller = self.env().caller();
+            self.ensure_owner()?;
+
+            if !self.roles.contains((role, account)) {
+                self.roles.insert((role, account), &());
+                self.env().emit_event(RoleGranted {
+                    role,
+                    account,
+                    sender: caller,
+                });
+            }
+
+            Ok(())
+        }
+
+        /// Revoke a role from an account.  Only the owner can revoke roles.
+        #[ink(message)]
+        pub fn revoke_role(&mut self,
+            role: Role,
+            account: AccountId) -> Result<(), Error> {
+
+            let caller = self.env().caller();
+            self.ensure_owner()?;
+
+            if self.roles.contains((role, account)) {
+                self.roles.remove((role, account));
+                self.env().emit_event(RoleRevoked {
+                    role,
+                    account,
+                    sender: caller,
+                });
+            }
+
+            Ok(())
+        }
+
+        /// Renounce a role held by the caller
+        #[ink(message)]
+        pub fn renounce_role(&mut self,
+            role: Role) -> Result<(), Error> {
+
+            let caller = self.env().caller();
+            if !self.roles.contains((role, caller)) {
+                return Err(self.fail(Error::RoleNotGranted));
+            }
+
+            self.roles.remove((role, caller));
+            self.env().emit_event(RoleRevoked {
+                role,
+                account: caller,
+                sender: caller,
+            });
+
+            Ok(())
+        }
+
+        /// Check if an account holds a role.  The manager holds every role.
+        #[ink(message)]
+        pub fn has_role(&self,
+            role: Role,
+            account: AccountId) -> bool {
+
+            account == self.manager || self.roles.contains((role, account))
+        }
+
+        /// Check that the caller holds a role
+        fn ensure_role(&self, role: Role) -> Result<(), Error> {
+            if !self.has_role(role, self.env().caller()) {
+                return Err(self.fail(Error::BadOrigin));
+            }
+            Ok(())
+        }
+
+        /// Check that the caller is the manager
+        fn ensure_manager(&self) -> Result<(), Error> {
+            if self.env().caller() != self.manager {
+                return Err(self.fail(Error::BadOrigin));
+            }
+            Ok(())
+        }
+
+        /// Check that a ledger is active
+        fn ensure_active(&self, ledger: &Ledger) -> Result<(), Error> {
+            match ledger.status {
+                AccountStatus::Active => Ok(()),
+                AccountStatus::Frozen => Err(self.fail(Error::AccountFrozen)),
+                AccountStatus::Dormant => Err(self.fail(Error::AccountDormant)),
+                AccountStatus::Closed => Err(self.fail(Error::AccountClosed)),
+            }
+        }
+
+        /// Change the status of an account.  Allowed transitions:
+        ///     Active, Dormant -> Frozen
+        ///     Frozen, Dormant -> Active
+        ///     Active          -> Dormant
+        fn change_account_status(&mut self,
+            account: AccountId,
+            to: AccountStatus,
+            reason: u8) -> Result<(), Error> {
+
+            let mut ledger = self.load_ledger(account)?;
+            let from = ledger.status;
+
+            let allowed = matches!(
+                (from, to),
+                (AccountStatus::Active, AccountStatus::Frozen)
+                    | (AccountStatus::Dormant, AccountStatus::Frozen)
+                    | (AccountStatus::Frozen, AccountStatus::Active)
+                    | (AccountStatus::Dormant, AccountStatus::Active)
+                    | (AccountStatus::Active, AccountStatus::Dormant)
+            );
+            if !allowed {
+                return Err(self.fail(Error::InvalidStatusTransition));
+            }
+
+            ledger.status = to;
+            self.ledgers.insert((self.ledger_epoch, account), &ledger);
+
+            self.env().emit_event(AccountStatusChanged {
+                account,
+                from,
+                to,
+                reason,
+            });
+
+            Ok(())
+        }
+
+        /// Check that the caller is the owner
+        fn ensure_owner(&self) -> Result<(), Error> {
+            if self.env().caller() != self.owner {
+                return Err(self.fail(Error::BadOrigin));
+            }
+            Ok(())
+        }
+
+        /// Set the bank asset
+        fn set_asset_id(&mut self, asset_id: u128) -> Result<(), Error> {
+            if self.treasury > 0 || self.interest_pool > 0 || self.interest_owed > 0
+                || self.interest_distribution.is_some() || self.has_balances() {
+                return Err(self.fail(Error::BankNotEmpty));
+            }
+            let old_value = self.asset_id;
+            self.asset_id = asset_id;
+            self.emit_parameter_changed(Parameter::AssetId, old_value, asset_id);
+            Ok(())
+        }
+
+        /// Set the bank loan asset
+        fn set_loan_asset_id(&mut self, loan_asset_id: u128) -> Result<(), Error> {
+            if self.loan_count > 0 {
+                return Err(self.fail(Error::LoansOutstanding));
+            }
+            let old_value = self.loan_asset_id;
+            self.loan_asset_id = loan_asset_id;
+            self.emit_parameter_changed(Parameter::LoanAssetId, old_value, loan_asset_id);
+            Ok(())
+        }
+
+        /// Set the maximum accounts
+        fn set_maximum_accounts(&mut self, maximum_accounts: u16) -> Result<(), Error> {
+            if u32::from(maximum_accounts) < self.ledger_count {
+                return Err(self.fail(Error::InvalidParameter));
+            }
+            let old_value = self.maximum_accounts;
+            self.maximum_accounts = maximum_accounts;
+            self.emit_parameter_changed(Parameter::MaximumAccounts, old_value.into(), maximum_accounts.into());
+            Ok(())
+        }
+
+        /// Set the loan price threshold
+        fn set_threshold(&mut self, threshold: u16) -> Result<(), Error> {
+            if threshold > 100 {
+                return Err(self.fail(Error::InvalidParameter));
+            }
+            let old_value = self.threshold;
+            self.threshold = threshold;
+            self.emit_parameter_changed(Parameter::Threshold, old_value.into(), threshold.into());
+            Ok(())
+        }
+
+        /// Set the daily blocks
+        fn set_daily_blocks(&mut self, daily_blocks: u16) -> Result<(), Error> {
+            if daily_blocks == 0 {
+                return Err(self.fail(Error::InvalidParameter));
+            }
+            // Count the interest days elapsed at the old daily blocks
+            self.interest_days = self.current_interest_days()?;
+            self.interest_days_block = self.env().block_number();
+            let old_value = self.daily_blocks;
+            self.daily_blocks = daily_blocks;
+            self.emit_parameter_changed(Parameter::DailyBlocks, old_value.into(), daily_blocks.into());
+            Ok(())
+        }
+
+        /// Emit a parameter changed event
+        fn emit_parameter_changed(&self, parameter: Parameter, old_value: u128, new_value: u128) {
+            self.env().emit_event(ParameterChanged {
+                parameter,
+                old_value,
+                new_value,
+            });
+        }
+
+        /// Check if any ledger holds a free or locked balance
+        fn has_balances(&self) -> bool {
+            self.total_balances > 0
+        }
+
+        /// Fail with an error, emitting the error event if error events are enabled
+        fn fail(&self, error: Error) -> Error {
+            if self.emit_errors {
+                self.env().emit_event(BankingEvent {
+                    operator: self.env().caller(),
+                    status: BankTransactionStatus::EmitError(error.clone()),
+                });
+            }
+            error
+        }
+
+        /// Check if an amount needs the approval of the approvers
+        fn requires_approval(&self, amount: u128) -> bool {
+            self.approval_threshold > 0 && amount > self.approval_amount
+        }
+
+        /// Create a proposal for an action.  The proposer's approval is recorded if the
+        /// proposer is an approver.  Returns `None` without storing the proposal if that
+        /// approval already reaches the approval threshold, the caller then executes
+        /// the action directly.
+        fn create_proposal(&mut self, action: ProposalAction) -> Result<Option<u32>, Error> {
+            let caller = self.env().caller();
+            let id = self.next_proposal_id;
+            let expiry_block = self.env().block_number()
+                .checked_add(self.proposal_lifetime)
+                .ok_or_else(|| self.fail(Error::InvalidParameter))?;
+
+            let mut approvals = Vec::new();
+            if self.approvers.get_or_default().contains(&caller) {
+                approvals.push(caller);
+            }
+
+            // Approved by the proposer alone, the action executes without a proposal
+            if approvals.len() >= usize::from(self.approval_threshold) {
+                return Ok(None);
+            }
+
+            self.next_proposal_id = id.wrapping_add(1);
+
+            self.env().emit_event(ProposalCreated {
+                id,
+                proposer: caller,
+                action: action.clone(),
+                expiry_block,
+            });
+
+            self.proposals.insert(id, &Proposal {
+                id,
+                action,
+                proposer: caller,
+                approvals,
+                expiry_block,
+            });
+
+            Ok(Some(id))
+        }
+
+        /// Deposit execution, shared by `deposit` and approved proposals
+        fn execute_deposit(&mut self,
+            account: AccountId,
+            amount: u128) -> Result<(), Error> {
+
+            let caller = self.env().caller();
+
+            // Check if the bank is open
+            if self.status != 0 {
+                return Err(self.fail(Error::BankIsClose));
+            }
+
+            self.deposit_to_ledger(account, amount)?;
+
+            self.env().emit_event(BankingEvent {
+                operator: caller,
+                status: BankTransactionStatus::EmitSuccess(Success::AccountDepositSuccess),
+            });
+
+            Ok(())
+        }
+
+        /// Withdraw execution, shared by `withdraw` and approved proposals
+        fn execute_withdraw(&mut self,
+            account: AccountId,
+            amount: u128) -> Result<(), ContractError> {
+
+            let caller = self.env().caller();
+
+            // Check if the bank is open
+            if self.status != 0 {
+                return Err(self.fail(Error::BankIsClose).into());
+            }
+
+            // Search if the account exist already, if it does, check if the balance is
+            // sufficient, if so, deduct the ledger, if not raise a balance insufficient
+            // error.
+            let mut ledger = self.load_ledger(account)?;
+
+            // Check if account is active
+            self.ensure_active(&ledger)?;
+
+            // Check if balance is sufficient
+            if ledger.balance < amount {
+                return Err(self.fail(Error::AccountBalanceInsufficient).into());
+            }
+
+            // Deduct the amount
+            let balance = ledger.balance - amount;
+            self.set_balance(&mut ledger, balance)?;
+
+            self.ledgers.insert((self.ledger_epoch, account), &ledger);
+
+            // Transfer the asset to the account
+            self.env()
+                .call_runtime(&RuntimeCall::Assets(AssetsCall::Transfer {
+                    id: self.asset_id,
+                    target: account.into(),
+                    amount,
+                }))
+                .map_err(|_| RuntimeError::CallRuntimeFailed)?;
+
+            self.env().emit_event(BankingEvent {
+                operator: caller,
+                status: BankTransactionStatus::EmitSuccess(Success::AccountWithdrawalSuccess),
+            });
+
+            Ok(())
+        }
+
+        /// Credit execution, shared by `credit` and approved proposals
+        fn execute_credit(&mut self,
+            account: AccountId,
+            amount: u128) -> Result<(), Error> {
+
+            let caller = self.env().caller();
+
+            // Check if the bank is open
+            if self.status != 0 {
+                return Err(self.fail(Error::BankIsClose));
+            }
+
+            // Search for the caller account in the ledger, if found, add to the balance
+            // the given amount.
+            let mut ledger = self.load_ledger(account)?;
+
+            // Check if account is active
+            self.ensure_active(&ledger)?;
+
+            // Add the amount to the balance safely
+            let balance = ledger.balance
+                .checked_add(amount)
+                .ok_or_else(|| self.fail(Error::AccountBalanceOverflow))?;
+            self.set_balance(&mut ledger, balance)?;
+
+            self.ledgers.insert((self.ledger_epoch, account), &ledger);
+
+            self.env().emit_event(BankingEvent {
+                operator: caller,
+                status: BankTransactionStatus::EmitSuccess(Success::AccountCreditSuccess),
+            });
+
+            Ok(())
+        }
+
+        /// Loan application execution, shared by `loan_application` and approved proposals
+        fn execute_loan_application(&mut self,
+            account: AccountId,
+            loan_amount: u128,
+            price: u128,
+            collateral: u128) -> Result<(), ContractError> {
+
+            let caller = self.env().caller();
+
+            // Check if the bank is open
+            if self.status != 0 {
+                return Err(self.fail(Error::BankIsClose).into());
+            } 
+
+            // Check if the account is a depositor
+            let mut ledger = self.load_ledger(account)?;
+
+            // Check if the account is active
+            self.ensure_active(&ledger)?;
+
+            // Check if the balance can cover the collateral
+            if collateral > ledger.balance {
+                return Err(self.fail(Error::AccountBalanceInsufficient).into());
+            }
+
+            // Check if the collateral can cover the liquidation price
+            // 1. compute for the threshold price: current price plus the threshold.
+            // 2. use the threshold price to compute for the collateral value
+            let threshold_price = price
+                .checked_add(
+                    price
+                        .checked_mul(self.threshold.into())
+                        .ok_or_else(|| self.fail(Error::LoanComputationOverflow))?
+                        .checked_div(100)
+                        .ok_or_else(|| self.fail(Error::LoanComputationOverflow))?
+                )
+                .ok_or_else(|| self.fail(Error::LoanComputationOverflow))?;
+
+            let collateral_value = collateral
+                .checked_mul(threshold_price)
+                .ok_or_else(|| self.fail(Error::LoanComputationOverflow))?;
+
+            if loan_amount > collateral_value {
+                return Err(self.fail(Error::LoanCollateralInsufficient).into());
+            }
+
+            // Check if there is an existing loan, if there is none then add a loan
+            if self.loans.contains((self.ledger_epoch, account)) {
+                return Err(self.fail(Error::LoanAlreadyExist).into());
+            }
+
+            // Now add the loan, but first compute for the liquidation price.
+            //      liquidation_price = (loan + threshold) / collateral
+            let loan_with_threshold = loan_amount
+                .checked_add(
+                    loan_amount
+                        .checked_mul(self.threshold as u128)
+                        .ok_or_else(|| self.fail(Error::LoanComputationOverflow))?
+                        .checked_div(100)
+                        .ok_or_else(|| self.fail(Error::LoanComputationOverflow))?
+                )
+                .ok_or_else(|| self.fail(Error::LoanComputationOverflow))?;
+
+            let liquidation_price = loan_with_threshold
+                .checked_div(collateral)
+                .ok_or_else(|| self.fail(Error::LoanComputationOverflow))?;
+
+            // Lock the collateral: move it from the free balance to the locked balance
+            let balance = ledger.balance
+                .checked_sub(collateral)
+                .ok_or_else(|| self.fail(Error::AccountBalanceInsufficient))?;
+            self.set_balance(&mut ledger, balance)?;
+            let locked = ledger.locked
+                .checked_add(collateral)
+                .ok_or_else(|| self.fail(Error::LoanComputationOverflow))?;
+            self.set_locked(&mut ledger, locked)?;
+            self.ledgers.insert((self.ledger_epoch, account), &ledger);
+
+            self.loans.insert((self.ledger_epoch, account), &Loan {
+                account,
+                collateral,
+                loan_amount,
+                paid_amount: 0,
+                balance: loan_amount,
+                liquidation_price,
+            });
+            self.add_loan_index(account);
+
+            // Disburse the loan amount to the borrower in the loan asset
+            self.env()
+                .call_runtime(&RuntimeCall::Assets(AssetsCall::Transfer {
+                    id: self.loan_asset_id,
+                    target: account.into(),
+                    amount: loan_amount,
+                }))
+                .map_err(|_| RuntimeError::CallRuntimeFailed)?;
+
+            // Success
+            self.env().emit_event(BankingEvent {
+                operator: caller,
+                status: BankTransactionStatus::EmitSuccess(Success::LoanApplicationSuccess),
+            });
+
+            Ok(())
+        }
+
+        /// Add a deposit to the account ledger, creating the ledger if it does not
+        /// exist yet.
+        fn deposit_to_ledger(&mut self,
+            account: AccountId,
+            amount: u128) -> Result<(), Error> {
+
+            let existing = match self.ledgers.contains((self.ledger_epoch, account)) {
+                true => Some(self.load_ledger(account)?),
+                false => None,
+            };
+            let mut ledger = match existing {
+                Some(mut ledger) => {
+                    // Frozen accounts cannot receive deposits, a deposit reactivates a
+                    // dormant account
+                    match ledger.status {
+                        AccountStatus::Active => {},
+                        AccountStatus::Dormant => {
+                            ledger.status = AccountStatus::Active;
+                            self.env().emit_event(AccountStatusChanged {
+                                account,
+                                from: AccountStatus::Dormant,
+                                to: AccountStatus::Active,
+                                reason: 0,
+                            });
+                        },
+                        _ => self.ensure_active(&ledger)?,
+                    }
+                    ledger
+                },
+                // Create a new account if the account does not exist
+                None => self.create_ledger(account, None)?,
+            };
+
+            let balance = ledger
+                .balance
+                .checked_add(amount)
+                .ok_or_else(|| self.fail(Error::AccountBalanceOverflow))?;
+            self.set_balance(&mut ledger, balance)?;
+
+            self.ledgers.insert((self.ledger_epoch, account), &ledger);
+
+            Ok(())
+        }
+
+        /// Rate tiers of a product version, no tiers if none were set
+        fn rate_schedule(&self,
+            product: u8,
+            version: u32) -> RateSchedule {
+            self.rate_schedules.get((product, version)).unwrap_or_default()
+        }
+
+        /// Interest days elapsed up to the current block
+        fn current_interest_days(&self) -> Result<u128, Error> {
+            let blocks_elapsed = self.env().block_number()
+                .saturating_sub(self.interest_days_block);
+
+            u128::from(blocks_elapsed)
+                .checked_mul(INTEREST_INDEX_SCALE)
+                .and_then(|days| days.checked_div(self.daily_blocks.into()))
+                .and_then(|days| days.checked_add(self.interest_days))
+                .ok_or_else(|| self.fail(Error::AccountBalanceOverflow))
+        }
+
+        /// Interest index of a product rate tier at the given interest days: one plus the
+        /// interest earned per unit of balance since the tier took effect (fixed point).
+        /// The index stops growing once the next version of the product rate tiers takes
+        /// effect.  The daily rate is derived from the annual rate on use.
+        fn tier_index(&self,
+            product: u8,
+            version: u32,
+            tier: u8,
+            days: u128) -> Option<u128> {
+
+            let schedule = self.rate_schedule(product, version);
+            let rate_bps = match schedule.tiers.get(usize::from(tier)) {
+                Some(rate_tier) => u128::from(rate_tier.rate_bps),
+                None => return Some(INTEREST_INDEX_SCALE),
+            };
+
+            let end_days = if version < self.rate_versions.get(product).unwrap_or(0) {
+                self.rate_schedule(product, version + 1).start_days
+            } else {
+                days
+            };
+            let days_elapsed = days.min(end_days)
+                .saturating_sub(schedule.start_days);
+
+            rate_bps
+                .checked_mul(days_elapsed)?
+                .checked_div(BASIS_POINTS * DAYS_PER_YEAR)?
+                .checked_add(INTEREST_INDEX_SCALE)
+        }
+
+        /// `a * b / c` rounded down, multiplied in 256 bits so that only a result that does
+        /// not fit in 128 bits overflows
+        fn mul_div(a: u128,
+            b: u128,
+            c: u128) -> Option<u128> {
+            multiply_by_rational_with_rounding(a, b, c, Rounding::Down)
+        }
+
+        /// Rate tier of a balance: the first tier whose `up_to` is not below the balance
+        fn rate_tier(tiers: &[RateTier],
+            balance: u128) -> u8 {
+            tiers.iter()
+                .position(|tier| balance <= tier.up_to)
+                .unwrap_or(tiers.len()) as u8
+        }
+
+        /// Interest earned by a ledger from its last settlement up to the given interest
+        /// days, following every change of its product rate tiers since, plus the interest
+        /// accrued under older versions.  Only active accounts earn interest.
+        fn pending_interest(&self,
+            ledger: &Ledger,
+            days: u128) -> Option<u128> {
+
+            if ledger.status != AccountStatus::Active {
+                return Some(ledger.accrued_interest);
+            }
+
+            let current_version = self.rate_versions.get(ledger.product).unwrap_or(0);
+
+            let mut interest = ledger.accrued_interest;
+            for version in ledger.rate_version..=current_version {
+                // Tier indices of newer tiers start at the scale
+                let (tier, from_index) = if version == ledger.rate_version {
+                    (ledger.rate_tier, ledger.interest_index)
+                } else {
+                    let schedule = self.rate_schedule(ledger.product, version);
+                    if schedule.start_days >= days {
+                        break;
+                    }
+                    (Self::rate_tier(&schedule.tiers, ledger.balance), INTEREST_INDEX_SCALE)
+                };
+                let to_index = self.tier_index(ledger.product, version, tier, days)?;
+
+                interest = Self::mul_div(ledger.balance,
+                    to_index.saturating_sub(from_index),
+                    INTEREST_INDEX_SCALE)?
+                    .checked_add(interest)?;
+            }
+
+            Some(interest)
+        }
+
+        /// Settle the interest earned by a ledger up to the given interest days against an
+        /// interest pool and the interest owed out of it.  The deferred interest is paid
+        /// first, pro-rata to the interest owed if the pool cannot cover it all, then the
+        /// new interest up to the part of the pool not owed.  The interest left unpaid is
+        /// deferred until the pool is funded.  Returns the interest paid.
+        fn accrue_interest(&self,
+            ledger: &mut Ledger,
+            days: u128,
+            pool: &mut u128,
+            owed: &mut u128) -> Option<u128> {
+
+            // Only active accounts are paid interest
+            if ledger.status != AccountStatus::Active {
+                return Some(0);
+            }
+
+            let earned = self.pending_interest(ledger, days)?;
+            ledger.accrued_interest = 0;
+            let deferred = ledger.deferred_interest;
+            let (paid_deferred, available) = match pool.checked_sub(*owed) {
+                Some(available) => (deferred, available),
+                None => (Self::mul_div(*pool, deferred, *owed)?, 0),
+            };
+            let paid_earned = earned.min(available);
+            let paid = paid_deferred.checked_add(paid_earned)?;
+
+            ledger.deferred_interest = (deferred - paid_deferred)
+                .checked_add(earned - paid_earned)?;
+            *pool = pool.checked_sub(paid)?;
+            *owed = owed.checked_sub(deferred)?
+                .checked_add(ledger.deferred_interest)?;
+
+            Some(paid)
+        }
+
+        /// Pay the interest earned by a ledger up to the given interest days out of the
+        /// interest pool, deferring what the pool cannot pay (see `accrue_interest`)
+        fn pay_interest(&mut self,
+            ledger: &mut Ledger,
+            days: u128) -> Result<u128, Error> {
+
+            let (mut pool, mut owed) = (self.interest_pool, self.interest_owed);
+            let interest = self.accrue_interest(ledger, days, &mut pool, &mut owed)
+                .ok_or_else(|| self.fail(Error::AccountBalanceOverflow))?;
+            self.interest_pool = pool;
+            self.interest_owed = owed;
+
+            Ok(interest)
+        }
+
+        /// Point a ledger at the tier index of its product and balance at the given
+        /// interest days
+        fn snapshot_interest_index(&self,
+            ledger: &mut Ledger,
+            days: u128) -> Result<(), Error> {
+
+            // Latest version of the product rate tiers in effect at the interest days
+            let current_version = self.rate_versions.get(ledger.product).unwrap_or(0);
+            let mut version = ledger.rate_version.min(current_version);
+            while version < current_version
+                && self.rate_schedule(ledger.product, version + 1).start_days <= days {
+                version += 1;
+            }
+            let tiers = self.rate_schedule(ledger.product, version).tiers;
+            let tier = Self::rate_tier(&tiers, ledger.balance);
+
+            ledger.rate_version = version;
+            ledger.rate_tier = tier;
+            ledger.interest_index = self.tier_index(ledger.product, version, tier, days)
+                .ok_or_else(|| self.fail(Error::AccountBalanceOverflow))?;
+
+            Ok(())
+        }
+
+        /// Credit the interest earned by a ledger since it was last settled, paid out of
+        /// the interest pool.  The interest earned in the statement periods closed since is
+        /// settled in those periods first.
+        fn settle_interest(&mut self,
+            ledger: &mut Ledger) -> Result<(), Error> {
+
+            self.rollover_statements(ledger)?;
+
+            let days = self.current_interest_days()?;
+            let interest = self.pay_interest(ledger, days)?;
+
+            if interest > 0 {
+                let balance = ledger.balance
+                    .checked_add(interest)
+                    .ok_or_else(|| self.fail(Error::AccountBalanceOverflow))?;
+                self.set_balance(ledger, balance)?;
+
+                ledger.period_interest = ledger.period_interest
+                    .checked_add(interest)
+                    .ok_or_else(|| self.fail(Error::AccountBalanceOverflow))?;
+            } else {
+                self.snapshot_interest_index(ledger, days)?;
+            }
+
+            Ok(())
+        }
+
+        /// Get a ledger with its interest settled, for changes
+        fn load_ledger(&mut self,
+            account: AccountId) -> Result<Ledger, Error> {
+
+            let mut ledger = self.ledgers.get((self.ledger_epoch, account))
+                .ok_or_else(|| self.fail(Error::AccountNotFound))?;
+            self.settle_interest(&mut ledger)?;
+
+            Ok(ledger)
+        }
+
+        /// Settle the interest of a ledger and save it
+        fn settle_ledger(&mut self,
+            account: AccountId) -> Result<(), Error> {
+
+            let ledger = self.load_ledger(account)?;
+            self.ledgers.insert((self.ledger_epoch, account), &ledger);
+
+            Ok(())
+        }
+
+        /// Get a ledger with its pending interest added to the balance, for queries.  Like
+        /// the statement rollover, the interest of every period closed since the ledger
+        /// was last touched is settled at the period end first.
+        fn ledger_with_interest(&self,
+            account: AccountId) -> Option<Ledger> {
+
+            let mut ledger = self.ledgers.get((self.ledger_epoch, account))?;
+            let (mut pool, mut owed) = (self.interest_pool, self.interest_owed);
+
+            let mut period = ledger.period;
+            while period < self.current_period {
+                let end_days = self.period_end_days.get(period)?;
+                let interest = self.accrue_interest(&mut ledger, end_days, &mut pool, &mut owed)?;
+                ledger.balance = ledger.balance.checked_add(interest)?;
+                self.snapshot_interest_index(&mut ledger, end_days).ok()?;
+                period += 1;
+            }
+
+            let days = self.current_interest_days().ok()?;
+            let interest = self.accrue_interest(&mut ledger, days, &mut pool, &mut owed)?;
+            ledger.balance = ledger.balance.checked_add(interest)?;
+            self.snapshot_interest_index(&mut ledger, days).ok()?;
+
+            Some(ledger)
+        }
+
+        /// Accumulate the balance-blocks held up to a block and recompute the ADB
+        fn accrue_adb(&self,
+            ledger: &mut Ledger,
+            current_block: u128) -> Result<(), Error> {
+
+            let blocks_elapsed = current_block
+                .saturating_sub(ledger.adb_last_block);
+
+            ledger.cumulative_balance = ledger.balance
+                .checked_mul(blocks_elapsed)
+                .and_then(|balance_blocks| ledger.cumulative_balance.checked_add(balance_blocks))
+                .ok_or_else(|| self.fail(Error::AccountBalanceOverflow))?;
+            ledger.adb_last_block = current_block;
+
+            ledger.adb = ledger.cumulative_balance
+                .saturating_sub(ledger.period_start_cumulative)
+                .checked_div(current_block.saturating_sub(ledger.period_start_block))
+                .unwrap_or(0);
+
+            Ok(())
+        }
+
+        /// Change the free balance of a ledger.  The balance held so far is accumulated
+        /// first and the new balance is recorded in an ADB checkpoint.  Every free balance
+        /// change must go through here.
+        fn set_balance(&mut self,
+            ledger: &mut Ledger,
+            balance: u128) -> Result<(), Error> {
+
+            self.rollover_statements(ledger)?;
+            self.write_balance(ledger, balance, self.env().block_number().into())?;
+
+            self.snapshot_interest_index(ledger, self.current_interest_days()?)
+        }
+
+        /// Change the locked balance of a ledger, keeping the bank-wide total
+        fn set_locked(&mut self,
+            ledger: &mut Ledger,
+            locked: u128) -> Result<(), Error> {
+
+            self.total_balances = self.total_balances
+                .checked_sub(ledger.locked)
+                .and_then(|total| total.checked_add(locked))
+                .ok_or_else(|| self.fail(Error::AccountBalanceOverflow))?;
+            ledger.locked = locked;
+
+            Ok(())
+        }
+
+        /// Record a free balance change of a ledger at a block in an ADB checkpoint.  The
+        /// block must not be before the last checkpoint of the ledger.
+        fn write_balance(&mut self,
+            ledger: &mut Ledger,
+            balance: u128,
+            block: u128) -> Result<(), Error> {
+
+            self.accrue_adb(ledger, block)?;
+            self.total_balances = self.total_balances
+                .checked_sub(ledger.balance)
+                .and_then(|total| total.checked_add(balance))
+                .ok_or_else(|| self.fail(Error::AccountBalanceOverflow))?;
+            ledger.balance = balance;
+
+            let checkpoint = AdbCheckpoint {
+                block: ledger.adb_last_block,
+                cumulative_balance: ledger.cumulative_balance,
+                balance,
+            };
+
+            // Several changes in the same block share a checkpoint
+            let last = ledger.checkpoint_count.checked_sub(1);
+            match last.and_then(|index| self.adb_checkpoints.get((ledger.account, index))) {
+                Some(previous) if previous.block == checkpoint.block => {
+                    self.adb_checkpoints.insert((ledger.account, ledger.checkpoint_count - 1), &checkpoint);
+                },
+                _ => {
+                    self.adb_checkpoints.insert((ledger.account, ledger.checkpoint_count), &checkpoint);
+                    ledger.checkpoint_count = ledger.checkpoint_count
+                        .checked_add(1)
+                        .ok_or_else(|| self.fail(Error::AccountBalanceOverflow))?;
+                },
+            }
+
+            Ok(())
+        }
+
+        /// Last ADB checkpoint of a ledger at or before a block, found by binary search
+        fn checkpoint_at(&self,
+            ledger: &Ledger,
+            block: u128) -> Option<AdbCheckpoint> {
+
+            // Number of checkpoints at or before the block
+            let mut low: u32 = 0;
+            let mut high = ledger.checkpoint_count;
+            while low < high {
+                let middle = low + (high - low) / 2;
+                let checkpoint = self.adb_checkpoints.get((ledger.account, middle))?;
+                if checkpoint.block <= block {
+                    low = middle + 1;
+                } else {
+                    high = middle;
+                }
+            }
+
+            self.adb_checkpoints.get((ledger.account, low.checked_sub(1)?))
+        }
+
+        /// Cumulative balance-blocks of a ledger at a block
+        fn cumulative_balance_at(&self,
+            ledger: &Ledger,
+            block: u128) -> Option<u128> {
+
+            // No balance before the first checkpoint
+            let Some(checkpoint) = self.checkpoint_at(ledger, block) else {
+                return Some(0);
+            };
+
+            checkpoint.balance
+                .checked_mul(block - checkpoint.block)?
+                .checked_add(checkpoint.cumulative_balance)
+        }
+
+        /// Balance-blocks held by a ledger over a closed statement period
+        fn balance_blocks_in(&self,
+            ledger: &Ledger,
+            period: u32) -> Option<u128> {
+
+            let start_block: u128 = match period.checked_sub(1) {
+                Some(previous) => self.period_end_blocks.get(previous)?.into(),
+                None => 0,
+            };
+            let end_block: u128 = self.period_end_blocks.get(period)?.into();
+
+            self.cumulative_balance_at(ledger, end_block)?
+                .checked_sub(self.cumulative_balance_at(ledger, start_block)?)
+        }
+
+        /// Free balance of a ledger held during a block
+        fn balance_at(&self,
+            ledger: &Ledger,
+            block: u128) -> u128 {
+            self.checkpoint_at(ledger, block)
+                .map(|checkpoint| checkpoint.balance)
+                .unwrap_or(0)
+        }
+
+        /// Build the statement of a closed period the ledger has not rolled over yet
+        fn build_statement(&self,
+            ledger: &Ledger,
+            period: u32) -> Option<Statement> {
+
+            if period < ledger.period || period >= self.current_period {
+                return None;
+            }
+
+            let start_block = if period == ledger.period {
+                ledger.period_start_block
+            } else {
+                self.period_end_blocks.get(period - 1)?.into()
+            };
+            let end_block: u128 = self.period_end_blocks.get(period)?.into();
+
+            // The interest earned up to the end of the period is settled at its end block
+            let end_interest = if period == ledger.period {
+                self.period_end_interest(ledger, period)?
+            } else {
+                0
+            };
+
+            let opening_balance = match (period.checked_sub(1), start_block.checked_sub(1)) {
+                (Some(previous), Some(block)) => self.statements.get((ledger.account, previous))
+                    .map(|statement| statement.closing_balance)
+                    .unwrap_or_else(|| self.balance_at(ledger, block)),
+                (None, Some(block)) => self.balance_at(ledger, block),
+                _ => 0,
+            };
+            let closing_balance = self.balance_at(ledger, end_block.checked_sub(1)?)
+                .checked_add(end_interest)?;
+
+            let adb = self.cumulative_balance_at(ledger, end_block)?
+                .checked_sub(self.cumulative_balance_at(ledger, start_block)?)?
+                .checked_div(end_block.saturating_sub(start_block))
+                .unwrap_or(0);
+
+            let interest_paid = if period == ledger.period {
+                ledger.period_interest.checked_add(end_interest)?
+            } else {
+                0
+            };
+
+            Some(Statement {
+                period,
+                start_block,
+                end_block,
+                opening_balance,
+                closing_balance,
+                adb,
+                interest_paid,
+            })
+        }
+
+        /// Interest paid to a ledger from its last settlement up to the end of a closed
+        /// statement period, out of the interest pool
+        fn period_end_interest(&self,
+            ledger: &Ledger,
+            period: u32) -> Option<u128> {
+
+            let mut ledger = ledger.clone();
+            let (mut pool, mut owed) = (self.interest_pool, self.interest_owed);
+            self.accrue_interest(&mut ledger, self.period_end_days.get(period)?, &mut pool, &mut owed)
+        }
+
+        /// Snapshot the statements of the periods closed since the ledger was last
+        /// touched and reset its ADB accumulator to the current period.  A ledger behind
+        /// by more than the maximum rollover periods must be rolled over first (see
+        /// `rollover_account`).
+        fn rollover_statements(&mut self,
+            ledger: &mut Ledger) -> Result<(), Error> {
+
+            if self.rollover_pending(ledger) {
+                return Err(self.fail(Error::RolloverPending));
+            }
+
+            self.rollover_periods(ledger, MAXIMUM_ROLLOVER_PERIODS)
+        }
+
+        /// Whether a ledger is behind by more than the maximum rollover periods or the
+        /// maximum rate tier versions a settlement follows
+        fn rollover_pending(&self,
+            ledger: &Ledger) -> bool {
+            let current_version = self.rate_versions.get(ledger.product).unwrap_or(0);
+            self.current_period.saturating_sub(ledger.period) > MAXIMUM_ROLLOVER_PERIODS
+                || current_version.saturating_sub(ledger.rate_version) > MAXIMUM_RATE_VERSIONS
+        }
+
+        /// Move a ledger one step closer to the current period and rate tiers: to the next
+        /// version of its product rate tiers if it took effect before the end of the ledger
+        /// period, else to the next period.  The interest earned up to the version start is
+        /// accrued, not paid, so the interest is the same as in a single settlement.
+        /// Returns whether the ledger moved.
+        fn rollover_step(&mut self,
+            ledger: &mut Ledger) -> Result<bool, Error> {
+
+            let end_days = match ledger.period < self.current_period {
+                true => Some(self.period_end_days.get(ledger.period)
+                    .ok_or_else(|| self.fail(Error::AccountBalanceOverflow))?),
+                false => None,
+            };
+
+            if ledger.rate_version < self.rate_versions.get(ledger.product).unwrap_or(0) {
+                let start_days = self.rate_schedule(ledger.product, ledger.rate_version + 1)
+                    .start_days;
+                if end_days.is_none_or(|end_days| start_days < end_days) {
+                    ledger.accrued_interest = self.pending_interest(ledger, start_days)
+                        .ok_or_else(|| self.fail(Error::AccountBalanceOverflow))?;
+                    self.snapshot_interest_index(ledger, start_days)?;
+                    return Ok(true);
+                }
+            }
+
+            if end_days.is_some() {
+                self.rollover_periods(ledger, 1)?;
+                return Ok(true);
+            }
+
+            Ok(false)
+        }
+
+        /// Snapshot the statements of at most `limit` periods closed since the ledger was
+        /// last touched.  The interest earned up to the end of every period is settled in
+        /// it, at its end block.
+        fn rollover_periods(&mut self,
+            ledger: &mut Ledger,
+            limit: u32) -> Result<(), Error> {
+
+            let end_period = ledger.period.saturating_add(limit).min(self.current_period);
+            while ledger.period < end_period {
+                let statement = self.build_statement(ledger, ledger.period)
+                    .ok_or_else(|| self.fail(Error::AccountBalanceOverflow))?;
+                self.statements.insert((ledger.account, ledger.period), &statement);
+
+                let end_days = self.period_end_days.get(ledger.period)
+                    .ok_or_else(|| self.fail(Error::AccountBalanceOverflow))?;
+                let interest = self.pay_interest(ledger, end_days)?;
+                if interest > 0 {
+                    let balance = ledger.balance
+                        .checked_add(interest)
+                        .ok_or_else(|| self.fail(Error::AccountBalanceOverflow))?;
+                    let block = statement.end_block.max(ledger.adb_last_block);
+                    self.write_balance(ledger, balance, block)?;
+                }
+                self.snapshot_interest_index(ledger, end_days)?;
+
+                ledger.period_start_cumulative = self.cumulative_balance_at(ledger, statement.end_block)
+                    .ok_or_else(|| self.fail(Error::AccountBalanceOverflow))?;
+                ledger.period_start_block = statement.end_block;
+                ledger.period_interest = 0;
+                ledger.period += 1;
+            }
+
+            Ok(())
+        }
+
+        /// Create an empty ledger and register it in the ledger account index
+        fn create_ledger(&mut self,
+            account: AccountId,
+            metadata: Option<Hash>) -> Result<Ledger, Error> {
+
+            if self.ledger_count >= self.maximum_accounts.into() {
+                return Err(self.fail(Error::BankAccountMaxOut));
+            }
+
+            let mut ledger = Ledger {
+                account,
+                balance: 0,
+                locked: 0,
+                adb: 0,
+                adb_beginning_block: self.env().block_number() as u128,
+                cumulative_balance: 0,
+                adb_last_block: self.env().block_number() as u128,
+                checkpoint_count: 0,
+                period: self.current_period,
+                period_start_block: self.env().block_number() as u128,
+                period_start_cumulative: 0,
+                period_interest: 0,
+                product: 0,
+                rate_version: self.rate_versions.get(0).unwrap_or(0),
+                rate_tier: 0,
+                interest_index: INTEREST_INDEX_SCALE,
+                deferred_interest: 0,
+                accrued_interest: 0,
+                status: AccountStatus::Active,
+                metadata,
+            };
+            self.snapshot_interest_index(&mut ledger, self.current_interest_days()?)?;
+            self.ledgers.insert((self.ledger_epoch, account), &ledger);
+            self.add_ledger_index(account);
+
+            Ok(ledger)
+        }
+
+        /// Move a non-zero amount of free balance from one ledger to another.  Both
+        /// accounts must be active, the recipient is opened if it does not exist and
+        /// `auto_open` is set.
+        fn transfer_between(&mut self,
+            from: AccountId,
+            to: AccountId,
+            amount: u128,
+            auto_open: bool) -> Result<(), Error> {
+
+            if from == to || amount == 0 {
+                return Err(self.fail(Error::InvalidParameter));
+            }
+
+            self.ensure_can_receive(to, auto_open)?;
+            self.debit_ledger(from, amount)?;
+            self.credit_ledger(to, amount, auto_open)?;
+
+            self.env().emit_event(Transfer {
+                from,
+                to,
+                amount,
+            });
+
+            Ok(())
+        }
+
+        /// Check that a ledger can be credited before anything is debited.  The ledger
+        /// must be active, or not exist with `auto_open` set and room for a new account.
+        fn ensure_can_receive(&self,
+            account: AccountId,
+            auto_open: bool) -> Result<(), Error> {
+
+            match self.ledgers.get((self.ledger_epoch, account)) {
+                Some(ledger) => self.ensure_active(&ledger),
+                None if !auto_open => Err(self.fail(Error::AccountNotFound)),
+                None if self.ledger_count >= self.maximum_accounts.into() => {
+                    Err(self.fail(Error::BankAccountMaxOut))
+                },
+                None => Ok(()),
+            }
+        }
+
+        /// Deduct from the free balance of an active ledger
+        fn debit_ledger(&mut self,
+            account: AccountId,
+            amount: u128) -> Result<Ledger, Error> {
+
+            let mut ledger = self.load_ledger(account)?;
+            self.ensure_active(&ledger)?;
+            if ledger.balance < amount {
+                return Err(self.fail(Error::AccountBalanceInsufficient));
+            }
+
+            let balance = ledger.balance - amount;
+            self.set_balance(&mut ledger, balance)?;
+
+            self.ledgers.insert((self.ledger_epoch, account), &ledger);
+
+            Ok(ledger)
+        }
+
+        /// Add to the free balance of an active ledger.  The ledger is opened if it does
+        /// not exist and `auto_open` is set.
+        fn credit_ledger(&mut self,
+            account: AccountId,
+            amount: u128,
+            auto_open: bool) -> Result<Ledger, Error> {
+
+            let existing = match self.ledgers.contains((self.ledger_epoch, account)) {
+                true => Some(self.load_ledger(account)?),
+                false => None,
+            };
+            let mut ledger = match existing {
+                Some(ledger) => ledger,
+                None if auto_open => self.create_ledger(account, None)?,
+                None => return Err(self.fail(Error::AccountNotFound)),
+            };
+            self.ensure_active(&ledger)?;
+
+            let balance = ledger.balance
+                .checked_add(amount)
+                .ok_or_else(|| self.fail(Error::AccountBalanceOverflow))?;
+            self.set_balance(&mut ledger, balance)?;
+
+            self.ledgers.insert((self.ledger_epoch, account), &ledger);
+
+            Ok(ledger)
+        }
+
+        /// Check that a transfer between two ledgers would succeed
+        fn can_transfer(&self,
+            from: AccountId,
+            to: AccountId,
+            amount: u128) -> bool {
+
+            // A ledger too far behind is rejected before its interest is priced
+            let current = |account| self.ledgers.get((self.ledger_epoch, account))
+                .is_some_and(|ledger| !self.rollover_pending(&ledger));
+            if from == to || !current(from) || !current(to) {
+                return false;
+            }
+
+            let sender = self.ledger_with_interest(from);
+            let recipient = self.ledger_with_interest(to);
+            match (sender, recipient) {
+                (Some(sender), Some(recipient)) => {
+                    sender.status == AccountStatus::Active
+                        && recipient.status == AccountStatus::Active
+                        && sender.balance >= amount
+                        && recipient.balance.checked_add(amount).is_some()
+                },
+                _ => false,
+            }
+        }
+
+        /// Standing order interval in blocks
+        fn order_interval(&self,
+            interval_days: u16) -> Result<BlockNumber, Error> {
+            BlockNumber::from(interval_days)
+                .checked_mul(self.daily_blocks.into())
+                .ok_or_else(|| self.fail(Error::InvalidParameter))
+        }
+
+        /// Get a standing order, orders cleared by a reset are not found
+        fn standing_order(&self,
+            id: u32) -> Option<StandingOrder> {
+
+            if id < self.first_order_id {
+                return None;
+            }
+            self.standing_orders.get(id)
+        }
+
+        /// Get the standing order ids of an owner, without the orders cleared by a reset
+        fn order_ids_of(&self,
+            owner: AccountId) -> Vec<u32> {
+
+            let mut order_ids = self.orders_by_owner.get(owner).unwrap_or_default();
+            order_ids.retain(|id| *id >= self.first_order_id);
+            order_ids
+        }
+
+        /// Get an escrow, escrows cleared by a reset are not found
+        fn escrow(&self,
+            id: u32) -> Option<Escrow> {
+
+            if id < self.first_escrow_id {
+                return None;
+            }
+            self.escrows.get(id)
+        }
+
+        /// Get the escrow ids of a payer, without the escrows cleared by a reset
+        fn escrow_ids_of(&self,
+            payer: AccountId) -> Vec<u32> {
+
+            let mut escrow_ids = self.escrows_by_payer.get(payer).unwrap_or_default();
+            escrow_ids.retain(|id| *id >= self.first_escrow_id);
+            escrow_ids
+        }
+
+        /// Remove a standing order and its owner index entry
+        fn remove_standing_order(&mut self,
+            order: &StandingOrder) {
+
+            self.standing_orders.remove(order.id);
+
+            let mut order_ids = self.order_ids_of(order.owner);
+            order_ids.retain(|id| *id != order.id);
+            if order_ids.is_empty() {
+                self.orders_by_owner.remove(order.owner);
+            } else {
+                self.orders_by_owner.insert(order.owner, &order_ids);
+            }
+        }
+
+        /// Remove an escrow and its payer index entry, and release the escrowed amount
+        /// from the payer's locked balance
+        fn unlock_escrow(&mut self,
+            escrow: &Escrow) -> Result<(), Error> {
+
+            let mut ledger = self.ledgers.get((self.ledger_epoch, escrow.payer))
+                .ok_or_else(|| self.fail(Error::AccountNotFound))?;
+            let locked = ledger.locked
+                .checked_sub(escrow.amount)
+                .ok_or_else(|| self.fail(Error::AccountBalanceInsufficient))?;
+            self.set_locked(&mut ledger, locked)?;
+            self.ledgers.insert((self.ledger_epoch, escrow.payer), &ledger);
+
+            self.escrows.remove(escrow.id);
+
+            let mut escrow_ids = self.escrow_ids_of(escrow.payer);
+            escrow_ids.retain(|id| *id != escrow.id);
+            if escrow_ids.is_empty() {
+                self.escrows_by_payer.remove(escrow.payer);
+            } else {
+                self.escrows_by_payer.insert(escrow.payer, &escrow_ids);
+            }
+
+            Ok(())
+        }
+
+        /// Store an allowance and emit the approval event
+        fn set_allowance(&mut self,
+            owner: AccountId,
+            spender: AccountId,
+            amount: u128) {
+
+            if amount == 0 {
+                self.allowances.remove((self.allowance_epoch, owner, spender));
+            } else {
+                self.allowances.insert((self.allowance_epoch, owner, spender), &amount);
+            }
+
+            self.env().emit_event(Approval {
+                owner,
+                spender,
+                amount,
+            });
+        }
+
+        /// Apply a payment (not exceeding the loan balance) to the loan of an account.
+        /// On full payment the loan is removed and the collateral is released back to
+        /// the free balance.  Returns true if the loan is fully paid.
+        fn apply_loan_payment(&mut self,
+            account: AccountId,
+            amount: u128) -> Result<bool, Error> {
+
+            let mut loan = self.loans.get((self.ledger_epoch, account)).ok_or_else(|| self.fail(Error::LoanNotFound))?;
+
+            // Update the paid amount and balance
+            loan.paid_amount = loan.paid_amount
+                .checked_add(amount)
+                .ok_or_else(|| self.fail(Error::LoanComputationOverflow))?;
+
+            // Recompute balance
+            loan.balance = loan.loan_amount
+                .checked_sub(loan.paid_amount)
+                .ok_or_else(|| self.fail(Error::LoanComputationOverflow))?;
+
+            if loan.balance > 0 {
+                self.loans.insert((self.ledger_epoch, account), &loan);
+                return Ok(false);
+            }
+
+            // Fully paid, release the collateral back to the free balance
+            let mut ledger = self.load_ledger(account)?;
+            let locked = ledger.locked
+                .checked_sub(loan.collateral)
+                .ok_or_else(|| self.fail(Error::LoanComputationOverflow))?;
+            self.set_locked(&mut ledger, locked)?;
+            let balance = ledger.balance
+                .checked_add(loan.collateral)
+                .ok_or_else(|| self.fail(Error::LoanComputationOverflow))?;
+            self.set_balance(&mut ledger, balance)?;
+            self.ledgers.insert((self.ledger_epoch, account), &ledger);
+
+            // Remove the loan
+            self.loans.remove((self.ledger_epoch, account));
+            self.remove_loan_index(account);
+
+            Ok(true)
+        }
+
+        /// Append an account to the ledger account index
+        fn add_ledger_index(&mut self, account: AccountId) {
+            self.ledger_accounts.insert(self.ledger_count, &account);
+            self.ledger_positions.insert((self.ledger_epoch, account), &self.ledger_count);
+            self.ledger_count += 1;
+        }
+
+        /// Remove an account from the ledger account index.  The last entry is
+        /// swapped into the freed position so the index stays contiguous.
+        fn remove_ledger_index(&mut self, account: AccountId) {
+            let position = match self.ledger_positions.take((self.ledger_epoch, account)) {
+                Some(position) => position,
+                None => return,
+            };
+            let last = self.ledger_count - 1;
+            if position != last {
+                if let Some(moved) = self.ledger_accounts.get(last) {
+                    self.ledger_accounts.insert(position, &moved);
+                    self.ledger_positions.insert((self.ledger_epoch, moved), &position);
+                }
+            }
+            self.ledger_accounts.remove(last);
+            self.ledger_count = last;
+        }
+
+        /// Append an account to the loan account index
+        fn add_loan_index(&mut self, account: AccountId) {
+            self.loan_accounts.insert(self.loan_count, &account);
+            self.loan_positions.insert((self.ledger_epoch, account), &self.loan_count);
+            self.loan_count += 1;
+        }
+
+        /// Remove an account from the loan account index.  The last entry is
+        /// swapped into the freed position so the index stays contiguous.
+        fn remove_loan_index(&mut self, account: AccountId) {
+            let position = match self.loan_positions.take((self.ledger_epoch, account)) {
+                Some(position) => position,
+                None => return,
+            };
+            let last = self.loan_count - 1;
+            if position != last {
+                if let Some(moved) = self.loan_accounts.get(last) {
+                    self.loan_accounts.insert(position, &moved);
+                    self.loan_positions.insert((self.ledger_epoch, moved), &position);
+                }
+            }
+            self.loan_accounts.remove(last);
+            self.loan_count = last;
+        }
+
+    }
+
+    /// Unit tests
+    #[cfg(test)]
+    mod tests {
+        /// Imports all the definitions from the outer scope so we can use them here.
+        use super::*;
+
+        /// We test if the default constructor does its job.
+        #[ink::test]
+        fn default_works() {
+            let Bank = Bank::default();
+        }
+
+        /// We test that a deposit creates a ledger and registers it in the account index.
+        #[ink::test]
+        fn deposit_creates_ledger() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut bank = Bank::new(1, 2, 10, 5, 10);
+
+            assert_eq!(bank.deposit(accounts.bob, 100), Ok(None));
+            assert_eq!(bank.deposit(accounts.bob, 50), Ok(None));
+
+            let ledger = bank.get_balance(accounts.bob).expect("ledger exists");
+            assert_eq!(ledger.balance, 150);
+            assert_eq!(bank.ledger_count, 1);
+            assert_eq!(bank.ledger_accounts.get(0), Some(accounts.bob));
+        }
+
+        /// We test that a granted role allows the message and a revoked role does not.
+        #[ink::test]
+        fn teller_role_works() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut bank = Bank::new(1, 2, 10, 5, 10);
+
+            assert_eq!(bank.grant_role(Role::Teller, accounts.bob), Ok(()));
+            assert!(bank.has_role(Role::Teller, accounts.bob));
+            assert!(!bank.has_role(Role::Pauser, accounts.bob));
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(bank.deposit(accounts.charlie, 100), Ok(None));
+            assert_eq!(bank.close(), Err(Error::BadOrigin));
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(bank.revoke_role(Role::Teller, accounts.bob), Ok(()));
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(bank.deposit(accounts.charlie, 100), Err(Error::BadOrigin));
+        }
+
+        /// We test that a frozen account cannot debit and can debit again once unfrozen.
+        #[ink::test]
+        fn freeze_and_unfreeze_works() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut bank = Bank::new(1, 2, 10, 5, 10);
+            assert_eq!(bank.deposit(accounts.bob, 100), Ok(None));
+
+            assert_eq!(bank.freeze_account(accounts.bob, 1), Ok(()));
+            assert_eq!(bank.freeze_account(accounts.bob, 1), Err(Error::InvalidStatusTransition));
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(bank.debit(10), Err(Error::AccountFrozen));
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(bank.set_account_product(accounts.bob, 0), Err(Error::AccountFrozen));
+            assert_eq!(bank.unfreeze_account(accounts.bob, 2), Ok(()));
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(bank.debit(10), Ok(()));
+            assert_eq!(bank.get_balance(accounts.bob).map(|l| l.balance), Some(90));
+        }
+
+        /// We test that closing an account frees the ledger slot and keeps a tombstone.
+        #[ink::test]
+        fn open_and_close_account_works() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut bank = Bank::new(1, 2, 1, 5, 10);
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(bank.open_account(accounts.bob, None), Ok(()));
+            assert_eq!(bank.open_account(accounts.bob, None), Err(Error::AccountAlreadyExist));
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            assert_eq!(bank.open_account(accounts.charlie, None), Err(Error::BankAccountMaxOut));
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(bank.close_account(accounts.bob), Ok(()));
+            assert_eq!(bank.get_balance(accounts.bob), None);
+            assert_eq!(bank.ledger_count, 0);
+            assert_eq!(bank.get_closed_account(accounts.bob).map(|c| c.closed_by), Some(accounts.bob));
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            assert_eq!(bank.open_account(accounts.charlie, None), Ok(()));
+        }
+
+        /// We test the two-step ownership and manager handover.
+        #[ink::test]
+        fn ownership_and_manager_handover_works() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut bank = Bank::new(1, 2, 10, 5, 10);
+
+            // Ownership: only the pending owner can accept, a cancelled transfer is gone
+            assert_eq!(bank.transfer_ownership(accounts.charlie), Ok(()));
+            assert_eq!(bank.cancel_ownership_transfer(), Ok(()));
+            assert_eq!(bank.get_pending_transfers(), (None, None));
+            assert_eq!(bank.transfer_ownership(accounts.bob), Ok(()));
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            assert_eq!(bank.accept_ownership(), Err(Error::BadOrigin));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(bank.accept_ownership(), Ok(()));
+            assert_eq!(bank.get().1, accounts.bob);
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(bank.transfer_ownership(accounts.alice), Err(Error::BadOrigin));
+
+            // Manager: proposed through the timelock, accepted by the new manager
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            let id = bank.queue_change(TimelockedChange::Manager(accounts.charlie)).expect("change queued");
+            for _ in 0..10 {
+                ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            }
+            assert_eq!(bank.execute_change(id), Ok(()));
+            assert_eq!(bank.get_pending_transfers(), (None, Some(accounts.charlie)));
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.django);
+            assert_eq!(bank.accept_manager(), Err(Error::BadOrigin));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            assert_eq!(bank.accept_manager(), Ok(()));
+            assert_eq!(bank.get().2, accounts.charlie);
+            assert_eq!(bank.get_pending_transfers(), (None, None));
+
+            // The new manager holds every role, the old one no longer does
+            assert_eq!(bank.deposit(accounts.eve, 10), Ok(None));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(bank.deposit(accounts.eve, 10), Err(Error::BadOrigin));
+        }
+
+        /// We test the parameter validation and the two-step reset.
+        #[ink::test]
+        fn reconfiguration_and_reset_works() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut bank = Bank::new(1, 2, 10, 5, 10);
+            assert_eq!(bank.deposit(accounts.bob, 100), Ok(None));
+            assert_eq!(bank.deposit(accounts.charlie, 100), Ok(None));
+
+            // Parameters are validated against the existing accounts
+            assert_eq!(bank.update_maximum_accounts(1), Err(Error::InvalidParameter));
+            assert_eq!(bank.update_maximum_accounts(2), Ok(()));
+            assert_eq!(bank.update_asset_id(3), Err(Error::BankNotEmpty));
+            assert_eq!(bank.update_loan_asset_id(4), Ok(()));
+            assert_eq!(bank.get().0, 1);
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(bank.update_dust_limit(1), Err(Error::BadOrigin));
+            assert_eq!(bank.request_reset(), Err(Error::BadOrigin));
+
+            // The reset must be requested and confirmed in a later block
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(bank.confirm_reset(), Err(Error::ResetNotRequested));
+            assert_eq!(bank.request_reset(), Ok(()));
+            assert_eq!(bank.confirm_reset(), Err(Error::ResetNotRequested));
+            assert_eq!(bank.cancel_reset(), Ok(()));
+
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            assert_eq!(bank.confirm_reset(), Err(Error::ResetNotRequested));
+            assert_eq!(bank.get_balance(accounts.bob).map(|l| l.balance), Some(100));
+
+            // Escrows, standing orders and allowances do not survive the reset
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(bank.approve(accounts.django, 10), Ok(()));
+            assert_eq!(bank.create_standing_order(accounts.charlie, 10, 1, 100), Ok(0));
+            assert_eq!(bank.create_escrow(accounts.charlie, accounts.django, 10, 100), Ok(0));
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(bank.request_reset(), Ok(()));
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            assert_eq!(bank.confirm_reset(), Ok(()));
+            assert_eq!(bank.get_balance(accounts.bob), None);
+            assert_eq!(bank.allowance(accounts.bob, accounts.django), 0);
+            assert!(bank.get_standing_orders(accounts.bob).is_empty());
+            assert!(bank.get_escrows(accounts.bob).is_empty());
+            assert_eq!(bank.update_asset_id(3), Ok(()));
+            assert_eq!(bank.get().0, 3);
+
+            // Accounts are opened again in the new epoch
+            assert_eq!(bank.deposit(accounts.bob, 5), Ok(None));
+            assert_eq!(bank.get_balance(accounts.bob).map(|l| (l.balance, l.locked)), Some((5, 0)));
+            assert_eq!(bank.update_asset_id(4), Err(Error::BankNotEmpty));
+        }
+
+        /// We test that sensitive changes are queued and only executed after the delay.
+        #[ink::test]
+        fn timelocked_change_works() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut bank = Bank::new(1, 2, 10, 5, 10);
+
+            assert_eq!(bank.queue_change(TimelockedChange::Threshold(101)), Err(Error::InvalidParameter));
+            let first = bank.queue_change(TimelockedChange::Threshold(20)).expect("change queued");
+            let second = bank.queue_change(TimelockedChange::DailyBlocks(20)).expect("change queued");
+            let pending = bank.get_pending_changes();
+            assert_eq!(pending.len(), 2);
+            assert_eq!(pending[0].eta, 10);
+            assert_eq!(pending[0].proposer, accounts.alice);
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(bank.queue_change(TimelockedChange::Threshold(0)), Err(Error::BadOrigin));
+            assert_eq!(bank.cancel_change(second), Err(Error::BadOrigin));
+
+            // The owner cancels during the delay, nothing executes before the eta
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(bank.cancel_change(second), Ok(()));
+            assert_eq!(bank.execute_change(first), Err(Error::TimelockNotExpired));
+            for _ in 0..10 {
+                ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            }
+
+            // Anyone can execute a change once its delay has passed
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(bank.execute_change(second), Err(Error::ChangeNotFound));
+            assert_eq!(bank.execute_change(first), Ok(()));
+            assert_eq!(bank.get().4, 20);
+            assert_eq!(bank.get().5, 10);
+            assert!(bank.get_pending_changes().is_empty());
+
+            // The changes of the manager do not block the changes of the owner
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            let id = bank.queue_change(TimelockedChange::Manager(accounts.charlie)).expect("change queued");
+            for _ in 0..20 {
+                ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            }
+            assert_eq!(bank.execute_change(id), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            assert_eq!(bank.accept_manager(), Ok(()));
+            for _ in 0..MAXIMUM_PENDING_CHANGES {
+                assert!(bank.queue_change(TimelockedChange::Threshold(30)).is_ok());
+            }
+            assert_eq!(bank.queue_change(TimelockedChange::Threshold(30)), Err(Error::TooManyPendingChanges));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert!(bank.queue_change(TimelockedChange::Manager(accounts.django)).is_ok());
+        }
+
+        /// We test that high-value actions need the approval of the approvers.
+        #[ink::test]
+        fn multisig_proposal_works() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut bank = Bank::new(1, 2, 10, 5, 10);
+            assert_eq!(bank.set_approvers(vec![accounts.alice, accounts.bob], 2, 100, 10), Ok(()));
+
+            // Below the approval amount the action executes directly
+            assert_eq!(bank.deposit(accounts.charlie, 100), Ok(None));
+
+            // Above it a proposal is created, approved by the proposer
+            assert_eq!(bank.deposit(accounts.charlie, 500), Ok(Some(0)));
+            assert_eq!(bank.credit(accounts.charlie, 200), Ok(Some(1)));
+            assert_eq!(bank.get_balance(accounts.charlie).map(|l| l.balance), Some(100));
+            assert_eq!(bank.get_proposal(0).map(|p| p.approvals), Some(vec![accounts.alice]));
+            assert_eq!(bank.approve_proposal(0), Err(ContractError::Internal(Error::ProposalAlreadyApproved)));
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.django);
+            assert_eq!(bank.approve_proposal(0), Err(ContractError::Internal(Error::BadOrigin)));
+
+            // The second approval executes the action
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(bank.approve_proposal(0), Ok(()));
+            assert_eq!(bank.get_balance(accounts.charlie).map(|l| l.balance), Some(600));
+            assert_eq!(bank.get_proposal(0), None);
+
+            // An expired proposal can no longer be approved, anyone can cancel it
+            for _ in 0..11 {
+                ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            }
+            assert_eq!(bank.approve_proposal(1), Err(ContractError::Internal(Error::ProposalExpired)));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.django);
+            assert_eq!(bank.cancel_proposal(1), Ok(()));
+            assert_eq!(bank.get_proposal(1), None);
+
+            // With a threshold of one the proposer's approval executes the action
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(bank.set_approvers(vec![accounts.alice, accounts.bob], 1, 100, 10), Ok(()));
+            assert_eq!(bank.credit(accounts.charlie, 200), Ok(None));
+            assert_eq!(bank.get_balance(accounts.charlie).map(|l| l.balance), Some(800));
+
+            // No proposal id is used for an action executed directly
+            assert_eq!(bank.set_approvers(vec![accounts.alice, accounts.bob], 2, 100, 10), Ok(()));
+            assert_eq!(bank.credit(accounts.charlie, 200), Ok(Some(2)));
+
+            // The approvers are capped
+            let approvers = (0..=MAXIMUM_APPROVERS as u8).map(|i| AccountId::from([i; 32])).collect();
+            assert_eq!(bank.set_approvers(approvers, 2, 100, 10), Err(Error::TooManyApprovers));
+        }
+
+        /// We test that a transfer moves balance between ledgers and can open the recipient.
+        #[ink::test]
+        fn transfer_works() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut bank = Bank::new(1, 2, 10, 5, 10);
+            assert_eq!(bank.deposit(accounts.bob, 100), Ok(None));
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(bank.transfer(accounts.charlie, 40, false), Err(Error::AccountNotFound));
+            assert_eq!(bank.transfer(accounts.charlie, 0, true), Err(Error::InvalidParameter));
+            assert_eq!(bank.get_balance(accounts.charlie), None);
+            assert_eq!(bank.transfer(accounts.charlie, 40, true), Ok(()));
+            assert_eq!(bank.transfer(accounts.charlie, 70, false), Err(Error::AccountBalanceInsufficient));
+
+            assert_eq!(bank.get_balance(accounts.bob).map(|l| l.balance), Some(60));
+            assert_eq!(bank.get_balance(accounts.charlie).map(|l| l.balance), Some(40));
+        }
+
+        /// We test that a batch transfer is all-or-nothing.
+        #[ink::test]
+        fn batch_transfer_is_atomic() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut bank = Bank::new(1, 2, 10, 5, 10);
+            assert_eq!(bank.deposit(accounts.bob, 100), Ok(None));
+            assert_eq!(bank.deposit(accounts.charlie, 0), Ok(None));
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            let receipts = bank
+                .batch_transfer(vec![(accounts.charlie, 30), (accounts.django, 20)], true)
+                .expect("batch transfer works");
+            assert_eq!(receipts.len(), 2);
+            assert_eq!(receipts[1].balance, 20);
+            assert_eq!(bank.get_balance(accounts.bob).map(|l| l.balance), Some(50));
+
+            assert_eq!(
+                bank.batch_transfer(vec![(accounts.charlie, 10), (accounts.eve, 10)], false),
+                Err(Error::AccountNotFound)
+            );
+            assert_eq!(bank.get_balance(accounts.bob).map(|l| l.balance), Some(50));
+            assert_eq!(bank.get_balance(accounts.charlie).map(|l| l.balance), Some(30));
+            assert_eq!(bank.get_balance(accounts.eve), None);
+        }
+
+        /// We test that a spender can only transfer within its allowance.
+        #[ink::test]
+        fn allowance_works() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut bank = Bank::new(1, 2, 10, 5, 10);
+            assert_eq!(bank.deposit(accounts.bob, 100), Ok(None));
+            assert_eq!(bank.deposit(accounts.django, 0), Ok(None));
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(bank.approve(accounts.charlie, 30), Ok(()));
+            assert_eq!(bank.increase_allowance(accounts.charlie, 10), Ok(()));
+            assert_eq!(bank.allowance(accounts.bob, accounts.charlie), 40);
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            assert_eq!(bank.transfer_from(accounts.bob, accounts.django, 25), Ok(()));
+            assert_eq!(bank.allowance(accounts.bob, accounts.charlie), 15);
+            assert_eq!(bank.get_balance(accounts.django).map(|l| l.balance), Some(25));
+            assert_eq!(
+                bank.transfer_from(accounts.bob, accounts.django, 20),
+                Err(Error::AllowanceInsufficient)
+            );
+        }
+
+        /// We test that due standing orders are paid and unpaid ones are skipped.
+        #[ink::test]
+        fn standing_order_works() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut bank = Bank::new(1, 2, 10, 5, 1);
+            assert_eq!(bank.deposit(accounts.bob, 15), Ok(None));
+            assert_eq!(bank.deposit(accounts.charlie, 0), Ok(None));
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            let id = bank.create_standing_order(accounts.charlie, 10, 1, 10).expect("order created");
+            assert_eq!(bank.get_standing_orders(accounts.bob).len(), 1);
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.django);
+            assert_eq!(bank.execute_due_orders(0, 10), Ok(1));
+            assert_eq!(bank.get_balance(accounts.charlie).map(|l| l.balance), Some(0));
+
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            assert_eq!(bank.execute_due_orders(0, 10), Ok(1));
+            assert_eq!(bank.get_balance(accounts.charlie).map(|l| l.balance), Some(10));
+
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            assert_eq!(bank.execute_due_orders(0, 10), Ok(1));
+            assert_eq!(bank.get_standing_orders(accounts.bob)[0].failures, 1);
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(bank.cancel_standing_order(id), Ok(()));
+            assert!(bank.get_standing_orders(accounts.bob).is_empty());
+        }
+
+        /// We test that an escrow locks the payer's funds until released or refunded.
+        #[ink::test]
+        fn escrow_works() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut bank = Bank::new(1, 2, 10, 5, 10);
+            assert_eq!(bank.deposit(accounts.bob, 100), Ok(None));
+            assert_eq!(bank.deposit(accounts.charlie, 0), Ok(None));
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            let first = bank.create_escrow(accounts.charlie, accounts.django, 40, 5).expect("escrow created");
+            let second = bank.create_escrow(accounts.charlie, accounts.django, 20, 5).expect("escrow created");
+            let ledger = bank.get_balance(accounts.bob).expect("ledger exists");
+            assert_eq!((ledger.balance, ledger.locked), (40, 60));
+            assert_eq!(bank.get_escrows(accounts.bob).len(), 2);
+            assert!(bank.get_escrows(accounts.django).is_empty());
+            assert_eq!(bank.get_escrow(first).map(|escrow| escrow.arbiter), Some(accounts.django));
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.django);
+            assert_eq!(bank.release_escrow(first), Ok(()));
+            assert_eq!(bank.get_balance(accounts.charlie).map(|l| l.balance), Some(40));
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.eve);
+            assert_eq!(bank.refund_escrow(second), Err(Error::BadOrigin));
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            assert_eq!(bank.refund_escrow(second), Ok(()));
+            let ledger = bank.get_balance(accounts.bob).expect("ledger exists");
+            assert_eq!((ledger.balance, ledger.locked), (60, 0));
+            assert!(bank.get_escrows(accounts.bob).is_empty());
+
+            // Only the escrows of the payer are indexed and capped
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            for _ in 0..MAXIMUM_ESCROWS {
+                assert!(bank.create_escrow(accounts.charlie, accounts.django, 1, 5).is_ok());
+            }
+            assert_eq!(bank.create_escrow(accounts.charlie, accounts.django, 1, 5), Err(Error::TooManyEscrows));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(bank.deposit(accounts.eve, 10), Ok(None));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.eve);
+            assert!(bank.create_escrow(accounts.charlie, accounts.django, 1, 5).is_ok());
+        }
+
+        /// We test that the ADB is a time-weighted average of the balance.
+        #[ink::test]
+        fn adb_is_time_weighted() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut bank = Bank::new(1, 2, 10, 5, 10);
+
+            // 10 held for blocks 0-1, then 100 for blocks 2-3
+            assert_eq!(bank.deposit(accounts.bob, 10), Ok(None));
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            assert_eq!(bank.deposit(accounts.bob, 90), Ok(None));
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+
+            assert_eq!(bank.adb_of(accounts.bob, 0, 2), Some(10));
+            assert_eq!(bank.adb_of(accounts.bob, 2, 4), Some(100));
+            assert_eq!(bank.adb_of(accounts.bob, 0, 4), Some(55));
+            assert_eq!(bank.adb_of(accounts.bob, 0, 5), None);
+
+            // The late deposit is not averaged in as if held since opening
+            assert_eq!(bank.get_balance(accounts.bob).map(|l| l.adb), Some(10));
+        }
+
+        /// We test that closing a period snapshots statements and resets the ADB.
+        #[ink::test]
+        fn statement_period_works() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut bank = Bank::new(1, 2, 10, 5, 10);
+
+            assert_eq!(bank.deposit(accounts.bob, 10), Ok(None));
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            assert_eq!(bank.close_period(), Ok(0));
+
+            // The deposit rolls the account over to period 1
+            assert_eq!(bank.deposit(accounts.bob, 90), Ok(None));
+            let statement = bank.get_statement(accounts.bob, 0).expect("statement exists");
+            assert_eq!((statement.opening_balance, statement.closing_balance, statement.adb), (0, 10, 10));
+            assert_eq!(bank.get_balance(accounts.bob).map(|l| l.adb), Some(0));
+
+            // Period 1 is not snapshotted yet but can be queried
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            assert_eq!(bank.close_period(), Ok(1));
+            let statement = bank.get_statement(accounts.bob, 1).expect("statement exists");
+            assert_eq!((statement.opening_balance, statement.closing_balance, statement.adb), (10, 100, 100));
+            assert_eq!(bank.get_statement(accounts.bob, 2), None);
+        }
+
+        /// We test that an account far behind is rolled over in bounded calls first.
+        #[ink::test]
+        fn bounded_rollover_works() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut bank = Bank::new(1, 2, 10, 5, 10);
+            assert_eq!(bank.deposit(accounts.bob, 100), Ok(None));
+            for period in 0..MAXIMUM_ROLLOVER_PERIODS + 2 {
+                ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+                assert_eq!(bank.close_period(), Ok(period));
+            }
+
+            assert_eq!(bank.deposit(accounts.bob, 10), Err(Error::RolloverPending));
+            assert_eq!(bank.rollover_account(accounts.bob, 0), Err(Error::InvalidParameter));
+            assert_eq!(bank.rollover_account(accounts.bob, 4), Ok(false));
+            assert_eq!(bank.get_balance(accounts.bob).map(|l| l.period), Some(4));
+            assert_eq!(bank.deposit(accounts.bob, 10), Ok(None));
+            assert_eq!(bank.rollover_account(accounts.bob, 1), Ok(true));
+
+            let statement = bank.get_statement(accounts.bob, MAXIMUM_ROLLOVER_PERIODS + 1)
+                .expect("statement exists");
+            assert_eq!((statement.opening_balance, statement.closing_balance), (100, 100));
+        }
+
+        /// We test that the interest earned in a closed period is settled in its statement.
+        #[ink::test]
+        fn period_end_interest_works() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut bank = Bank::new(1, 2, 10, 5, 1);
+            bank.interest_pool = 1000;
+
+            // 36.5% a year with one block a day is 0.1% per block
+            let tiers = vec![RateTier { up_to: u128::MAX, rate_bps: 3650 }];
+            assert_eq!(bank.set_rate_tiers(0, tiers), Ok(()));
+            assert_eq!(bank.deposit(accounts.bob, 1000), Ok(None));
+            for _ in 0..10 {
+                ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            }
+            assert_eq!(bank.close_period(), Ok(0));
+            for _ in 0..10 {
+                ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            }
+
+            // The period interest is in the statement before and after the snapshot
+            let statement = bank.get_statement(accounts.bob, 0).expect("statement exists");
+            assert_eq!((statement.closing_balance, statement.interest_paid), (1010, 10));
+            assert_eq!(bank.deposit(accounts.bob, 1), Ok(None));
+            assert_eq!(bank.get_statement(accounts.bob, 0), Some(statement));
+
+            // The interest earned since the period end is paid in the next period
+            let ledger = bank.get_balance(accounts.bob).expect("ledger exists");
+            assert_eq!((ledger.balance, ledger.period_interest), (1021, 10));
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            assert_eq!(bank.close_period(), Ok(1));
+            let statement = bank.get_statement(accounts.bob, 1).expect("statement exists");
+            assert_eq!((statement.opening_balance, statement.closing_balance), (1010, 1022));
+        }
+
+        /// We test that the pending interest is priced period by period, like the rollover.
+        #[ink::test]
+        fn pending_interest_follows_rollover() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut bank = Bank::new(1, 2, 10, 5, 1);
+            bank.interest_pool = 1000;
+
+            // 0.1% per block up to 1005, nothing above
+            let tiers = vec![
+                RateTier { up_to: 1005, rate_bps: 3650 },
+                RateTier { up_to: u128::MAX, rate_bps: 0 },
+            ];
+            assert_eq!(bank.set_rate_tiers(0, tiers), Ok(()));
+            assert_eq!(bank.deposit(accounts.bob, 1000), Ok(None));
+            for _ in 0..10 {
+                ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            }
+            assert_eq!(bank.close_period(), Ok(0));
+            for _ in 0..10 {
+                ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            }
+
+            // The balance moves to the upper tier at the period end
+            assert_eq!(bank.get_balance(accounts.bob).map(|l| l.balance), Some(1010));
+            assert_eq!(bank.preview_interest(accounts.bob, 0), Some(10));
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(bank.debit(1010), Ok(()));
+            assert_eq!(bank.get_balance(accounts.bob).map(|l| l.balance), Some(0));
+        }
+
+        /// We test that interest accrues per block and is settled when the account is used,
+        /// paid out of the interest pool.
+        #[ink::test]
+        fn interest_index_works() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut bank = Bank::new(1, 2, 10, 5, 1);
+            bank.interest_pool = 15;
+
+            // 36.5% a year with one block a day is 0.1% per block
+            let tiers = vec![RateTier { up_to: u128::MAX, rate_bps: 3650 }];
+            assert_eq!(bank.set_rate_tiers(0, tiers), Ok(()));
+            assert_eq!(bank.deposit(accounts.bob, 1000), Ok(None));
+            for _ in 0..10 {
+                ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            }
+            assert_eq!(bank.get_balance(accounts.bob).map(|l| l.balance), Some(1010));
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(bank.debit(1010), Ok(()));
+            let ledger = bank.get_balance(accounts.bob).expect("ledger exists");
+            assert_eq!((ledger.balance, ledger.period_interest), (0, 10));
+            assert_eq!(bank.get_interest_pool(), (5, 0, 0));
+
+            // The interest the pool cannot pay is deferred until it is funded
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(bank.deposit(accounts.bob, 1000), Ok(None));
+            for _ in 0..10 {
+                ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            }
+            assert_eq!(bank.get_balance(accounts.bob).map(|l| l.balance), Some(1005));
+            assert_eq!(bank.preview_interest(accounts.bob, 10), Some(5));
+            assert_eq!(bank.deposit(accounts.bob, 1), Ok(None));
+            let ledger = bank.get_balance(accounts.bob).expect("ledger exists");
+            assert_eq!((ledger.balance, ledger.deferred_interest), (1006, 5));
+            assert_eq!(bank.get_interest_pool(), (0, 0, 5));
+
+            bank.interest_pool = 5;
+            assert_eq!(bank.get_balance(accounts.bob).map(|l| l.balance), Some(1011));
+            assert_eq!(bank.deposit(accounts.bob, 1), Ok(None));
+            assert_eq!(bank.get_interest_pool(), (0, 0, 0));
+        }
+
+        /// We test that the interest of 18 decimal balances settles without overflow.
+        #[ink::test]
+        fn interest_index_works_with_large_balances() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut bank = Bank::new(1, 2, 10, 5, 1);
+            let token: u128 = 1_000_000_000_000_000_000;
+            bank.interest_pool = 1_000_000 * token;
+
+            // 100,000 tokens at 3% a year, untouched for a year
+            let tiers = vec![RateTier { up_to: u128::MAX, rate_bps: 300 }];
+            assert_eq!(bank.set_rate_tiers(0, tiers), Ok(()));
+            assert_eq!(bank.deposit(accounts.bob, 100_000 * token), Ok(None));
+            for _ in 0..365 {
+                ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            }
+            assert_eq!(bank.get_balance(accounts.bob).map(|l| l.balance), Some(103_000 * token));
+            assert_eq!(bank.preview_interest(accounts.bob, 365), Some(6_090 * token));
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(bank.debit(1), Ok(()));
+            let ledger = bank.get_balance(accounts.bob).expect("ledger exists");
+            assert_eq!((ledger.balance, ledger.period_interest), (103_000 * token - 1, 3_000 * token));
+        }
+
+        /// We test that a change of the daily blocks keeps the annual rate.
+        #[ink::test]
+        fn daily_blocks_change_keeps_annual_rate() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut bank = Bank::new(1, 2, 10, 5, 1);
+            bank.interest_pool = 1000;
+
+            // 36.5% a year is 0.1% a day
+            let tiers = vec![RateTier { up_to: u128::MAX, rate_bps: 3650 }];
+            assert_eq!(bank.set_rate_tiers(0, tiers), Ok(()));
+            assert_eq!(bank.deposit(accounts.bob, 1000), Ok(None));
+
+            // One day at one block a day, then ten days at two blocks a day
+            let id = bank.queue_change(TimelockedChange::DailyBlocks(2)).expect("change queued");
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            assert_eq!(bank.execute_change(id), Ok(()));
+            for _ in 0..20 {
+                ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            }
+            assert_eq!(bank.get_balance(accounts.bob).map(|l| l.balance), Some(1011));
+            assert_eq!(bank.preview_interest(accounts.bob, 20), Some(21));
+        }
+
+        /// We test that the rate depends on the balance tier and the account product.
+        #[ink::test]
+        fn rate_tiers_work() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut bank = Bank::new(1, 2, 10, 5, 1);
+            bank.interest_pool = 1000;
+
+            // 0.1% per block up to 1000, 0.2% above
+            let tiers = vec![
+                RateTier { up_to: 1000, rate_bps: 3650 },
+                RateTier { up_to: u128::MAX, rate_bps: 7300 },
+            ];
+            assert_eq!(bank.set_rate_tiers(0, tiers), Ok(()));
+            assert_eq!(bank.deposit(accounts.bob, 500), Ok(None));
+            assert_eq!(bank.deposit(accounts.charlie, 5000), Ok(None));
+            assert_eq!(bank.preview_interest(accounts.bob, 10), Some(5));
+
+            for _ in 0..10 {
+                ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            }
+            assert_eq!(bank.preview_interest(accounts.bob, 0), Some(5));
+            assert_eq!(bank.get_balance(accounts.charlie).map(|l| l.balance), Some(5100));
+
+            // A product without interest
+            assert_eq!(bank.set_rate_tiers(1, vec![]), Ok(()));
+            assert_eq!(bank.set_account_product(accounts.charlie, 1), Ok(()));
+            for _ in 0..10 {
+                ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            }
+            assert_eq!(bank.get_balance(accounts.charlie).map(|l| l.balance), Some(5100));
+        }
+
+        /// We test that an account behind by more rate tier versions than a settlement
+        /// follows is rolled over first, and keeps the interest of every version.
+        #[ink::test]
+        fn rate_versions_rollover_works() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut bank = Bank::new(1, 2, 10, 5, 1);
+            bank.interest_pool = 1000;
+
+            // 0.1% per block under every version
+            let tiers = vec![RateTier { up_to: u128::MAX, rate_bps: 3650 }];
+            assert_eq!(bank.set_rate_tiers(0, tiers.clone()), Ok(()));
+            assert_eq!(bank.deposit(accounts.bob, 1000), Ok(None));
+            for _ in 0..MAXIMUM_RATE_VERSIONS + 8 {
+                ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+                assert_eq!(bank.set_rate_tiers(0, tiers.clone()), Ok(()));
+            }
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            assert_eq!(bank.close_period(), Ok(0));
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+
+            assert_eq!(bank.get_balance(accounts.bob).map(|l| l.balance), Some(1042));
+            assert_eq!(bank.deposit(accounts.bob, 1), Err(Error::RolloverPending));
+            assert_eq!(bank.rollover_account(accounts.bob, 10), Ok(false));
+            assert_eq!(bank.deposit(accounts.bob, 1), Ok(None));
+            assert_eq!(bank.get_balance(accounts.bob).map(|l| l.balance), Some(1043));
+            let statement = bank.get_statement(accounts.bob, 0).expect("statement exists");
+            assert_eq!((statement.closing_balance, statement.interest_paid), (1041, 41));
+        }
+
+        /// We test that the interest pool is split by the balance held over the last closed
+        /// period, a few ledgers per call, and the dust is carried.
+        #[ink::test]
+        fn interest_pool_distribution_works() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut bank = Bank::new(1, 2, 10, 5, 10);
+            assert_eq!(bank.deposit(accounts.bob, 100), Ok(None));
+            assert_eq!(bank.deposit(accounts.charlie, 300), Ok(None));
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+
+            // Funding pulls the asset through a runtime call, set the pool directly
+            bank.interest_pool = 101;
+            assert_eq!(bank.distribute_interest_pool(1), Err(Error::InvalidParameter));
+            assert_eq!(bank.close_period(), Ok(0));
+
+            // A deposit after the period end earns no share
+            assert_eq!(bank.deposit(accounts.django, 1000), Ok(None));
+
+            // Three ledgers are summed then paid, one per call
+            for _ in 0..5 {
+                assert_eq!(bank.distribute_interest_pool(1), Ok(false));
+            }
+            assert_eq!(bank.get_interest_pool(), (0, 0, 0));
+            assert_eq!(bank.get_interest_distribution().map(|d| (d.paying, d.distributed)), Some((true, 100)));
+            assert_eq!(bank.distribute_interest_pool(1), Ok(true));
+
+            assert_eq!(bank.get_balance(accounts.bob).map(|l| l.balance), Some(125));
+            assert_eq!(bank.get_balance(accounts.charlie).map(|l| l.balance), Some(375));
+            assert_eq!(bank.get_balance(accounts.django).map(|l| l.balance), Some(1000));
+            assert_eq!(bank.get_interest_pool(), (1, 1, 0));
+            assert_eq!(bank.get_interest_distribution(), None);
+        }
+
+        /// We test that only the ledgers summed are paid and that the ledger index cannot
+        /// move during a distribution.
+        #[ink::test]
+        fn interest_pool_distribution_pays_the_ledgers_summed() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut bank = Bank::new(1, 2, 10, 5, 10);
+            assert_eq!(bank.deposit(accounts.bob, 100), Ok(None));
+            assert_eq!(bank.deposit(accounts.charlie, 300), Ok(None));
+            assert_eq!(bank.deposit(accounts.django, 100), Ok(None));
+            assert_eq!(bank.mark_dormant(accounts.django, 1), Ok(()));
+            assert_eq!(bank.deposit(accounts.eve, 0), Ok(None));
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            assert_eq!(bank.close_period(), Ok(0));
+
+            // The dormant ledger is not summed
+            bank.interest_pool = 100;
+            assert_eq!(bank.distribute_interest_pool(4), Ok(false));
+            assert_eq!(bank.get_interest_distribution().map(|d| d.paying), Some(true));
+
+            // Reactivated during the distribution, it is still not paid
+            assert_eq!(bank.deposit(accounts.django, 1), Ok(None));
+            assert_eq!(bank.close_account(accounts.eve), Err(ContractError::Internal(Error::DistributionInProgress)));
+
+            assert_eq!(bank.distribute_interest_pool(4), Ok(true));
+            assert_eq!(bank.get_balance(accounts.bob).map(|l| l.balance), Some(125));
+            assert_eq!(bank.get_balance(accounts.charlie).map(|l| l.balance), Some(375));
+            assert_eq!(bank.get_balance(accounts.django).map(|l| l.balance), Some(101));
+            assert_eq!(bank.get_interest_pool(), (0, 0, 0));
+        }
+
+        /// We test that the shares of 18 decimal balances are computed without overflow and
+        /// that an aborted distribution returns the amount not paid to the pool.
+        #[ink::test]
+        fn interest_pool_distribution_can_be_aborted() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut bank = Bank::new(1, 2, 10, 5, 14400);
+            let token: u128 = 1_000_000_000_000_000_000;
+            assert_eq!(bank.deposit(accounts.bob, 100_000 * token), Ok(None));
+            assert_eq!(bank.deposit(accounts.charlie, 300_000 * token), Ok(None));
+            for _ in 0..14400 {
+                ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            }
+            assert_eq!(bank.close_period(), Ok(0));
+
+            // Both ledgers are summed, then bob is paid
+            bank.interest_pool = 1_000 * token;
+            for _ in 0..3 {
+                assert_eq!(bank.distribute_interest_pool(1), Ok(false));
+            }
+            assert_eq!(bank.get_balance(accounts.bob).map(|l| l.balance), Some(100_250 * token));
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(bank.abort_interest_distribution(), Err(Error::BadOrigin));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(bank.abort_interest_distribution(), Ok(()));
+            assert_eq!(bank.abort_interest_distribution(), Err(Error::InvalidParameter));
+            assert_eq!(bank.get_interest_distribution(), None);
+            assert_eq!(bank.get_interest_pool(), (750 * token, 0, 0));
+            assert_eq!(bank.get_balance(accounts.charlie).map(|l| l.balance), Some(300_000 * token));
+        }
+
+        /// We test that a distribution keeps the tier interest accrued by the ledgers.
+        #[ink::test]
+        fn interest_pool_distribution_keeps_accrued_interest() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut bank = Bank::new(1, 2, 10, 5, 1);
+
+            // 36.5% a year with one block a day is 0.1% per block
+            let tiers = vec![RateTier { up_to: u128::MAX, rate_bps: 3650 }];
+            assert_eq!(bank.set_rate_tiers(0, tiers), Ok(()));
+            assert_eq!(bank.deposit(accounts.charlie, 1000), Ok(None));
+            for _ in 0..10 {
+                ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            }
+            assert_eq!(bank.close_period(), Ok(0));
+
+            // The whole pool is distributed, the accrued interest is deferred
+            bank.interest_pool = 100;
+            assert_eq!(bank.distribute_interest_pool(1), Ok(false));
+            assert_eq!(bank.distribute_interest_pool(1), Ok(true));
+            let ledger = bank.get_balance(accounts.charlie).expect("ledger exists");
+            assert_eq!((ledger.balance, ledger.deferred_interest), (1100, 10));
+            assert_eq!(bank.get_interest_pool(), (0, 0, 10));
+
+            // The interest owed is not distributed but paid to the ledger
+            bank.interest_pool = 10;
+            assert_eq!(bank.distribute_interest_pool(1), Err(Error::InvalidParameter));
+            assert_eq!(bank.get_balance(accounts.charlie).map(|l| l.balance), Some(1110));
+        }
+
+        /// We test that a liquidation seizes the collateral into the treasury and records
+        /// the shortfall as bad debt.
+        #[ink::test]
+        fn loan_liquidation_works() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut bank = Bank::new(1, 2, 10, 5, 10);
+            assert_eq!(bank.deposit(accounts.bob, 150), Ok(None));
+            assert_eq!(bank.deposit(accounts.charlie, 100), Ok(None));
+
+            // Loan applications disburse through a runtime call, seed the loans directly
+            for (account, balance, liquidation_price) in [(accounts.bob, 300, 3), (accounts.charlie, 50, 1)] {
+                let mut ledger = bank.ledgers.get((bank.ledger_epoch, account)).expect("ledger exists");
+                ledger.balance -= 100;
+                ledger.locked = 100;
+                bank.ledgers.insert((bank.ledger_epoch, account), &ledger);
+                bank.loans.insert((bank.ledger_epoch, account), &Loan {
+                    account,
+                    collateral: 100,
+                    loan_amount: balance,
+                    paid_amount: 0,
+                    balance,
+                    liquidation_price,
+                });
+                bank.add_loan_index(account);
+            }
+
+            // Only the loan at or above the price is liquidated
+            assert_eq!(bank.loan_liquidation(2), Ok(()));
+            assert_eq!(bank.get_treasury(), (100, 100));
+            assert_eq!(bank.get_balance(accounts.bob).map(|l| (l.balance, l.locked)), Some((50, 0)));
+            assert_eq!(bank.get_balance(accounts.charlie).map(|l| l.locked), Some(100));
+            assert!(!bank.loans.contains((bank.ledger_epoch, accounts.bob)));
+            assert_eq!(bank.loan_count, 1);
+        }
+
+        /// We test that the collateral moves from the free to the locked balance and that
+        /// the locked balance cannot be spent.
+        #[ink::test]
+        fn locked_balance_cannot_be_spent() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut bank = Bank::new(1, 2, 10, 5, 10);
+            assert_eq!(bank.deposit(accounts.bob, 150), Ok(None));
+            assert_eq!(bank.deposit(accounts.charlie, 0), Ok(None));
+
+            // Loan applications disburse through a runtime call, lock the collateral and
+            // seed the loan directly
+            let mut ledger = bank.load_ledger(accounts.bob).expect("ledger exists");
+            assert_eq!(bank.set_balance(&mut ledger, 50), Ok(()));
+            assert_eq!(bank.set_locked(&mut ledger, 100), Ok(()));
+            bank.ledgers.insert((bank.ledger_epoch, accounts.bob), &ledger);
+            bank.loans.insert((bank.ledger_epoch, accounts.bob), &Loan {
+                account: accounts.bob,
+                collateral: 100,
+                loan_amount: 300,
+                paid_amount: 0,
+                balance: 300,
+                liquidation_price: 3,
+            });
+            bank.add_loan_index(accounts.bob);
+            assert_eq!(bank.get_balance(accounts.bob).map(|l| (l.balance, l.locked)), Some((50, 100)));
+            assert_eq!(bank.total_balances, 150);
+
+            // Only the free balance can be spent
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(bank.debit(51), Err(Error::AccountBalanceInsufficient));
+            assert_eq!(bank.self_withdraw(51), Err(ContractError::Internal(Error::AccountBalanceInsufficient)));
+            assert_eq!(bank.transfer(accounts.charlie, 51, false), Err(Error::AccountBalanceInsufficient));
+            assert_eq!(bank.transfer(accounts.charlie, 20, false), Ok(()));
+            assert_eq!(bank.debit(30), Ok(()));
+            assert_eq!(bank.get_balance(accounts.bob).map(|l| (l.balance, l.locked)), Some((0, 100)));
+            assert_eq!(bank.debit(1), Err(Error::AccountBalanceInsufficient));
+        }
+
+        /// We test that loan payments reduce the balance and the full payment releases the
+        /// collateral.
+        #[ink::test]
+        fn loan_payment_works() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut bank = Bank::new(1, 2, 10, 5, 10);
+            assert_eq!(bank.deposit(accounts.bob, 150), Ok(None));
+
+            // Loan applications disburse through a runtime call, seed the loan directly
+            let mut ledger = bank.ledgers.get((bank.ledger_epoch, accounts.bob)).expect("ledger exists");
+            ledger.balance -= 100;
+            ledger.locked = 100;
+            bank.ledgers.insert((bank.ledger_epoch, accounts.bob), &ledger);
+            bank.loans.insert((bank.ledger_epoch, accounts.bob), &Loan {
+                account: accounts.bob,
+                collateral: 100,
+                loan_amount: 300,
+                paid_amount: 0,
+                balance: 300,
+                liquidation_price: 3,
+            });
+            bank.add_loan_index(accounts.bob);
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(bank.loan_payment(accounts.bob, 100), Err(ContractError::Internal(Error::BadOrigin)));
+
+            // A partial payment keeps the collateral locked
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(bank.loan_payment(accounts.bob, 0), Err(ContractError::Internal(Error::InvalidParameter)));
+            assert_eq!(bank.loan_payment(accounts.bob, 100), Ok(()));
+            let loan = bank.loans.get((bank.ledger_epoch, accounts.bob)).expect("loan exists");
+            assert_eq!((loan.paid_amount, loan.balance), (100, 200));
+            assert_eq!(bank.get_balance(accounts.bob).map(|l| (l.balance, l.locked)), Some((50, 100)));
+
+            // The payment cannot exceed the outstanding balance
+            assert_eq!(bank.loan_payment(accounts.bob, 201), Err(ContractError::Internal(Error::InvalidParameter)));
+
+            // The full payment removes the loan and releases the collateral
+            assert_eq!(bank.loan_payment(accounts.bob, 200), Ok(()));
+            assert!(!bank.loans.contains((bank.ledger_epoch, accounts.bob)));
+            assert_eq!(bank.loan_count, 0);
+            assert_eq!(bank.get_balance(accounts.bob).map(|l| (l.balance, l.locked)), Some((150, 0)));
+            assert_eq!(bank.loan_payment(accounts.bob, 1), Err(ContractError::Internal(Error::LoanNotFound)));
+        }
+
+        /// We test that a failed message returns the error instead of `Ok`.
+        #[ink::test]
+        fn deposit_by_non_manager_fails() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut bank = Bank::new(1, 2, 10, 5, 10);
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(bank.deposit(accounts.bob, 100), Err(Error::BadOrigin));
+            assert_eq!(bank.get_balance(accounts.bob), None);
         }
     }
 